@@ -0,0 +1,278 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use aoc2021::export::Format;
+use aoc2021::parse::Mode;
+use aoc2021::solution::Part;
+use aoc2021::stats::Layout;
+
+pub const USAGE: &str = "\
+Usage:
+    aoc2021 run --day <N> [--part <1|2>] [--input <PATH>] [--strict | --lenient] [--option <NAME=VALUE>]...
+    aoc2021 export --day 2 [--part <1|2>] [--input <PATH>] [--format <csv|json|svg>] [--strict | --lenient]
+    aoc2021 validate --day 2 [--part <1|2>] [--input <PATH>] [--strict | --lenient] [--option <NAME=VALUE>]...
+    aoc2021 stats --day 3 [--input <PATH>] [--format <table|json>] [--strict | --lenient] [--option <NAME=VALUE>]...
+    aoc2021 list
+
+Reads the puzzle input from PATH, or from stdin when it is \"-\". Without --input the input is read from
+~/.cache/aoc2021/dayN.txt; when that is missing and AOC_SESSION holds a session token, it is
+downloaded from AOC_BASE_URL and cached. There is no default: only plain http is supported, so
+AOC_BASE_URL must be a TLS-terminating proxy on a loopback address (e.g. http://127.0.0.1:8080).
+Parsing is strict by default; --lenient skips malformed items and reports each one as a warning.
+export prints the day 2 path after every instruction (step, instruction, forward, depth, aim).
+It follows part 2 unless --part 1 is given, and writes csv unless --format json is given.
+--format svg draws horizontal position against depth with both parts overlaid.
+validate reports every step of the day 2 course that breaks a limit, and fails if there are any.
+stats prints, for each day 3 column, the count of ones and zeros, the share of ones and whether it
+is tied, then gamma, epsilon, oxygen and CO2 in binary, decimal and hex.
+
+Options:
+    window=<N>     day 1 part 2 sliding window size. Defaults to 3.
+    aggregate=<A>  day 1 window aggregate: sum, mean or max. Defaults to sum.
+    threshold=<N>  day 1 changes of at most N count as unchanged. Defaults to 0.
+    counts=<C>     day 1 output: increased, or all for increased/decreased/unchanged.
+    win=<RULES>    day 4 win rules, comma separated: rows, columns, diagonals, corners,
+                   blackout, mask:<ROWS> (e.g. mask:10001/01010/00100/01010/10001).
+                   Defaults to rows,columns.
+    oxygen-tie=<T> day 3 bit kept by the oxygen rating on a tie: one, zero or error. Defaults to one.
+    co2-tie=<T>    day 3 bit kept by the CO2 rating on a tie: one, zero or error. Defaults to zero.
+    width=<N>      day 3 report width; shorter lines are read as having lost leading zeros.
+                   Without it every line must be as wide as the first.
+    min-depth=<N>  validate: shallowest allowed depth. Defaults to 0, the surface.
+    max-depth=<N>  validate: deepest allowed depth.
+    max-aim=<N>    validate: largest allowed aim in either direction.
+    max-horizontal=<N>
+                   validate: furthest allowed horizontal position.";
+
+#[derive(PartialEq, Debug)]
+pub enum Command {
+    Run(RunArgs),
+    Export(ExportArgs),
+    Validate(RunArgs),
+    Stats(StatsArgs),
+    List,
+    Help
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Source {
+    Cache,
+    Stdin,
+    File(PathBuf)
+}
+
+#[derive(PartialEq, Debug)]
+pub struct RunArgs {
+    pub day: u8,
+    pub part: Option<Part>,
+    pub input: Source,
+    pub mode: Mode,
+    pub options: Vec<(String, String)>
+}
+
+impl RunArgs {
+    pub fn parts(&self) -> Vec<Part> {
+        self.part.map_or(Part::ALL.to_vec(), |part| vec![part])
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ExportArgs {
+    pub day: u8,
+    pub part: Part,
+    pub input: Source,
+    pub mode: Mode,
+    pub format: Format
+}
+
+#[derive(PartialEq, Debug)]
+pub struct StatsArgs {
+    pub day: u8,
+    pub input: Source,
+    pub mode: Mode,
+    pub layout: Layout,
+    pub options: Vec<(String, String)>
+}
+
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        Some("run") => parse_run(args).map(Command::Run),
+        Some("export") => parse_export(args).map(Command::Export),
+        Some("validate") => parse_run(args).map(Command::Validate),
+        Some("stats") => parse_stats(args).map(Command::Stats),
+        Some("list") => Ok(Command::List),
+        Some("help") | Some("--help") | Some("-h") | None => Ok(Command::Help),
+        Some(other) => Err(format!("unknown command \"{}\"", other))
+    }
+}
+
+fn parse_run<I: Iterator<Item = String>>(mut args: I) -> Result<RunArgs, String> {
+    let mut day = None;
+    let mut part = None;
+    let mut input = Source::Cache;
+    let mut mode = Mode::Strict;
+    let mut options = Vec::new();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", flag));
+        match flag.as_str() {
+            "--day" | "-d" => day = Some(parse_day(&value()?)?),
+            "--part" | "-p" => part = Some(parse_part(&value()?)?),
+            "--input" | "-i" => input = parse_source(value()?),
+            "--strict" => mode = Mode::Strict,
+            "--lenient" => mode = Mode::Lenient,
+            "--option" | "-o" => options.push(parse_option(&value()?)?),
+            _ => return Err(format!("unknown option \"{}\"", flag))
+        }
+    }
+    let day = day.ok_or("missing required option --day")?;
+    Ok(RunArgs { day, part, input, mode, options })
+}
+
+fn parse_export<I: Iterator<Item = String>>(mut args: I) -> Result<ExportArgs, String> {
+    let mut day = None;
+    let mut part = Part::Two;
+    let mut input = Source::Cache;
+    let mut mode = Mode::Strict;
+    let mut format = Format::Csv;
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", flag));
+        match flag.as_str() {
+            "--day" | "-d" => day = Some(parse_day(&value()?)?),
+            "--part" | "-p" => part = parse_part(&value()?)?,
+            "--input" | "-i" => input = parse_source(value()?),
+            "--format" | "-f" => format = Format::from_str(&value()?)?,
+            "--strict" => mode = Mode::Strict,
+            "--lenient" => mode = Mode::Lenient,
+            _ => return Err(format!("unknown option \"{}\"", flag))
+        }
+    }
+    let day = day.ok_or("missing required option --day")?;
+    Ok(ExportArgs { day, part, input, mode, format })
+}
+
+fn parse_stats<I: Iterator<Item = String>>(mut args: I) -> Result<StatsArgs, String> {
+    let mut day = None;
+    let mut input = Source::Cache;
+    let mut mode = Mode::Strict;
+    let mut layout = Layout::Table;
+    let mut options = Vec::new();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", flag));
+        match flag.as_str() {
+            "--day" | "-d" => day = Some(parse_day(&value()?)?),
+            "--input" | "-i" => input = parse_source(value()?),
+            "--format" | "-f" => layout = Layout::from_str(&value()?)?,
+            "--strict" => mode = Mode::Strict,
+            "--lenient" => mode = Mode::Lenient,
+            "--option" | "-o" => options.push(parse_option(&value()?)?),
+            _ => return Err(format!("unknown option \"{}\"", flag))
+        }
+    }
+    let day = day.ok_or("missing required option --day")?;
+    Ok(StatsArgs { day, input, mode, layout, options })
+}
+
+fn parse_source(path: String) -> Source {
+    if path == "-" { Source::Stdin } else { Source::File(PathBuf::from(path)) }
+}
+
+fn parse_option(value: &str) -> Result<(String, String), String> {
+    value.split_once('=')
+        .map(|(name, value)| (String::from(name), String::from(value)))
+        .ok_or(format!("invalid option \"{}\", expected NAME=VALUE", value))
+}
+
+fn parse_day(value: &str) -> Result<u8, String> {
+    value.parse::<u8>().map_err(|_| format!("invalid day \"{}\"", value))
+}
+
+fn parse_part(value: &str) -> Result<Part, String> {
+    match value {
+        "1" => Ok(Part::One),
+        "2" => Ok(Part::Two),
+        _ => Err(format!("invalid part \"{}\", expected 1 or 2", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use aoc2021::export::Format;
+    use aoc2021::parse::Mode;
+    use aoc2021::solution::Part;
+    use aoc2021::stats::Layout;
+    use crate::cli::{Command, ExportArgs, parse_args, RunArgs, Source, StatsArgs};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_run() {
+        let expected = RunArgs { day: 3, part: Some(Part::Two), input: Source::File(PathBuf::from("inputs/day3.txt")), mode: Mode::Strict, options: Vec::new() };
+        assert_eq!(Ok(Command::Run(expected)), parse_args(args("run --day 3 --part 2 --input inputs/day3.txt")));
+    }
+
+    #[test]
+    fn test_parse_run_stdin_and_both_parts() {
+        let parsed = parse_args(args("run -d 1 -i -")).unwrap();
+        let expected = RunArgs { day: 1, part: None, input: Source::Stdin, mode: Mode::Strict, options: Vec::new() };
+        assert_eq!(Command::Run(expected), parsed);
+        if let Command::Run(run) = parsed {
+            assert_eq!(vec![Part::One, Part::Two], run.parts());
+        }
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let expected = RunArgs { day: 4, part: Some(Part::One), input: Source::Cache, mode: Mode::Lenient, options: Vec::new() };
+        assert_eq!(Ok(Command::Run(expected)), parse_args(args("run --lenient --day 4 --part 1")));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let parsed = parse_args(args("run --day 1 -o window=2 --option counts=all")).unwrap();
+        let options = vec![(String::from("window"), String::from("2")), (String::from("counts"), String::from("all"))];
+        assert_eq!(Command::Run(RunArgs { day: 1, part: None, input: Source::Cache, mode: Mode::Strict, options }), parsed);
+        assert_eq!(Err(String::from("invalid option \"win\", expected NAME=VALUE")), parse_args(args("run --day 4 -o win")));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Err(String::from("missing required option --day")), parse_args(args("run --part 1")));
+        assert_eq!(Err(String::from("invalid part \"3\", expected 1 or 2")), parse_args(args("run --day 1 --part 3")));
+        assert_eq!(Err(String::from("missing value for --input")), parse_args(args("run --day 1 --input")));
+        assert_eq!(Err(String::from("unknown command \"walk\"")), parse_args(args("walk")));
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let expected = ExportArgs { day: 2, part: Part::Two, input: Source::Cache, mode: Mode::Strict, format: Format::Csv };
+        assert_eq!(Ok(Command::Export(expected)), parse_args(args("export --day 2")));
+        let expected = ExportArgs { day: 2, part: Part::One, input: Source::File(PathBuf::from("day2.txt")), mode: Mode::Lenient, format: Format::Json };
+        assert_eq!(Ok(Command::Export(expected)), parse_args(args("export -d 2 -p 1 -i day2.txt --lenient --format json")));
+        assert_eq!(Err(String::from("invalid format \"xml\", expected csv, json or svg")), parse_args(args("export -d 2 -f xml")));
+    }
+
+    #[test]
+    fn test_parse_validate() {
+        let options = vec![(String::from("max-depth"), String::from("900"))];
+        let expected = RunArgs { day: 2, part: Some(Part::Two), input: Source::Cache, mode: Mode::Strict, options };
+        assert_eq!(Ok(Command::Validate(expected)), parse_args(args("validate -d 2 -p 2 -o max-depth=900")));
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let expected = StatsArgs { day: 3, input: Source::Cache, mode: Mode::Strict, layout: Layout::Table, options: Vec::new() };
+        assert_eq!(Ok(Command::Stats(expected)), parse_args(args("stats --day 3")));
+        let options = vec![(String::from("width"), String::from("12"))];
+        let expected = StatsArgs { day: 3, input: Source::File(PathBuf::from("day3.txt")), mode: Mode::Lenient, layout: Layout::Json, options };
+        assert_eq!(Ok(Command::Stats(expected)), parse_args(args("stats -d 3 -i day3.txt --lenient -f json -o width=12")));
+    }
+
+    #[test]
+    fn test_parse_help() {
+        assert_eq!(Ok(Command::Help), parse_args(Vec::new()));
+        assert_eq!(Ok(Command::List), parse_args(args("list")));
+    }
+}
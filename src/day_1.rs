@@ -1,38 +1,290 @@
-pub fn count_increases(data_points: &str) -> u32 {
-    let holder = ConsecutiveCounter::new();
-    let result = data_points.lines()
-        .map(|maybe_number| maybe_number.parse::<u32>().unwrap())
-        .fold(holder, |acc, num| acc.handle(num));
-    result.increases
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, Lines};
+use crate::error::{Error, ParseError};
+use crate::parse::{number_error, ParseContext};
+use crate::solution::{Solution, StreamingSolution};
+
+pub struct Day1;
+
+pub type Reading = u64;
+
+// Upper bound on the `window` option, far beyond the length of any real sonar log.
+pub const MAX_WINDOW: usize = 1 << 24;
+
+pub struct Sweep {
+    pub readings: Vec<Reading>,
+    pub settings: Settings
 }
 
-pub fn count_windowed_increases(data_points: &str) -> usize {
-    data_points.lines()
-        .filter_map(|n| n.parse().ok())
-        .collect::<Vec<u32>>()
-        .windows(3)
-        .map(|window| window.iter().sum::<u32>())
-        .collect::<Vec<u32>>()
-        .windows(2)
-        .filter(|a| a[0] < a[1])
-        .count()
+// How the CLI compares windows: part 1 always uses single readings, part 2 the configured window size.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Settings {
+    pub window: WindowConfig,
+    pub all_counts: bool
+}
+
+impl Settings {
+    pub const OPTIONS: &'static [&'static str] = &["window", "aggregate", "threshold", "counts"];
+
+    fn from_options(context: &ParseContext) -> Result<Settings, Error> {
+        let mut window = WindowConfig::sum(3);
+        if let Some(value) = context.option("window") {
+            window.size = value.parse::<usize>().ok()
+                .filter(|size| (1..=MAX_WINDOW).contains(size))
+                .ok_or_else(|| Error::invalid_option("window", value, &format!("expected a whole number from 1 to {}", MAX_WINDOW)))?;
+        }
+        if let Some(value) = context.option("aggregate") {
+            window.aggregate = match value {
+                "sum" => Aggregate::Sum,
+                "mean" => Aggregate::Mean,
+                "max" => Aggregate::Max,
+                _ => return Err(Error::invalid_option("aggregate", value, "expected sum, mean or max"))
+            };
+        }
+        if let Some(value) = context.option("threshold") {
+            window.threshold = value.parse::<Reading>()
+                .map_err(|_| Error::invalid_option("threshold", value, "expected a whole number"))?;
+        }
+        let all_counts = match context.option("counts") {
+            None | Some("increased") => false,
+            Some("all") => true,
+            Some(value) => return Err(Error::invalid_option("counts", value, "expected increased or all"))
+        };
+        Ok(Settings { window, all_counts })
+    }
+
+    fn single(&self) -> WindowConfig {
+        WindowConfig { size: 1, ..self.window }
+    }
 
+    fn counts(&self, tally: Tally) -> Counts {
+        Counts { tally, all: self.all_counts }
+    }
+}
+
+// The increase count by default, or every category when `counts=all` is set.
+#[derive(Debug)]
+pub struct Counts {
+    pub tally: Tally,
+    all: bool
+}
+
+impl Display for Counts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.all {
+            write!(f, "{}", self.tally)
+        } else {
+            write!(f, "{}", self.tally.increased)
+        }
+    }
+}
+
+impl Solution for Day1 {
+    type Input = Sweep;
+    type Part1 = Counts;
+    type Part2 = Counts;
+
+    const OPTIONS: &'static [&'static str] = Settings::OPTIONS;
+
+    fn parse(input: &str, context: &mut ParseContext) -> Result<Self::Input, Error> {
+        let settings = Settings::from_options(context)?;
+        Ok(Sweep { readings: parse_readings(input, context)?, settings })
+    }
+
+    fn part1(sweep: &Self::Input) -> Result<Self::Part1, Error> {
+        let settings = &sweep.settings;
+        Ok(settings.counts(tally_windows(sweep.readings.iter().copied(), &settings.single())?))
+    }
+
+    fn part2(sweep: &Self::Input) -> Result<Self::Part2, Error> {
+        let settings = &sweep.settings;
+        Ok(settings.counts(tally_windows(sweep.readings.iter().copied(), &settings.window)?))
+    }
+}
+
+impl StreamingSolution for Day1 {
+    fn stream(reader: &mut dyn BufRead, context: &mut ParseContext) -> Result<(Self::Part1, Self::Part2), Error> {
+        let settings = Settings::from_options(context)?;
+        let mut single = WindowedCounter::new(settings.single())?;
+        let mut windowed = WindowedCounter::new(settings.window)?;
+        for reading in Readings::new(reader, context) {
+            let reading = reading?;
+            single = single.handle(reading);
+            windowed = windowed.handle(reading);
+        }
+        Ok((settings.counts(single.tally()), settings.counts(windowed.tally())))
+    }
+}
+
+pub fn parse_readings(data_points: &str, context: &mut ParseContext) -> Result<Vec<Reading>, Error> {
+    Readings::new(data_points.as_bytes(), context).collect()
+}
+
+fn parse_reading(maybe_number: &str, line: usize) -> Result<Reading, ParseError> {
+    maybe_number.parse::<Reading>()
+        .map_err(|err| ParseError::new(1, line, 1, maybe_number, number_error(&err)))
+}
+
+// Parses one reading per line as they are read, so arbitrarily long logs use constant memory.
+pub struct Readings<'a, R> {
+    lines: Lines<R>,
+    line: usize,
+    context: &'a mut ParseContext
+}
+
+impl<R: BufRead> Readings<'_, R> {
+    pub fn new(reader: R, context: &mut ParseContext) -> Readings<'_, R> {
+        Readings { lines: reader.lines(), line: 0, context }
+    }
+}
+
+impl<R: BufRead> Iterator for Readings<'_, R> {
+    type Item = Result<Reading, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let text = match self.lines.next()? {
+                Ok(text) => text,
+                Err(err) => return Some(Err(err.into()))
+            };
+            self.line += 1;
+            match self.context.recover(parse_reading(&text, self.line)) {
+                Ok(Some(reading)) => return Some(Ok(reading)),
+                Ok(None) => continue,
+                Err(err) => return Some(Err(err.into()))
+            }
+        }
+    }
+}
+
+pub fn count_increases<I: IntoIterator<Item = Reading>>(readings: I) -> Result<u64, Error> {
+    count_windowed_increases(readings, 1)
+}
+
+pub fn count_windowed_increases<I: IntoIterator<Item = Reading>>(readings: I, window: usize) -> Result<u64, Error> {
+    Ok(tally_windows(readings, &WindowConfig::sum(window))?.increased)
+}
+
+pub fn tally_windows<I: IntoIterator<Item = Reading>>(readings: I, config: &WindowConfig) -> Result<Tally, Error> {
+    Ok(readings.into_iter()
+        .fold(WindowedCounter::new(*config)?, |acc, num| acc.handle(num))
+        .tally())
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Aggregate {
+    Sum,
+    Mean,
+    Max
+}
+
+// A change between consecutive windows counts as unchanged unless it exceeds `threshold`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WindowConfig {
+    pub size: usize,
+    pub aggregate: Aggregate,
+    pub threshold: Reading
+}
+
+impl WindowConfig {
+    pub fn sum(size: usize) -> WindowConfig {
+        WindowConfig { size, aggregate: Aggregate::Sum, threshold: 0 }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Tally {
+    pub increased: u64,
+    pub decreased: u64,
+    pub unchanged: u64
+}
+
+impl Display for Tally {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} increased, {} decreased, {} unchanged", self.increased, self.decreased, self.unchanged)
+    }
+}
+
+// Keeps only the current window, comparing each full window's aggregate with the previous one.
+// Sums and deltas are i128, which cannot overflow for any window that fits in memory. The window
+// grows as readings arrive, so a size larger than the input costs no more than the input itself.
+pub struct WindowedCounter {
+    config: WindowConfig,
+    window: VecDeque<Reading>,
+    maxima: VecDeque<Reading>,
+    sum: i128,
+    aggregates: ConsecutiveCounter
+}
+
+impl WindowedCounter {
+    pub fn new(config: WindowConfig) -> Result<WindowedCounter, Error> {
+        if config.size == 0 {
+            return Err(Error::invalid_option("window", "0", "expected a positive whole number"));
+        }
+        // Means are compared as sums, so the threshold is scaled up by the window size instead.
+        let threshold = match config.aggregate {
+            Aggregate::Mean => i128::from(config.threshold).checked_mul(config.size as i128)
+                .ok_or_else(|| Error::invalid_option("threshold", &config.threshold.to_string(),
+                                                     &format!("too large for a mean over {} readings", config.size)))?,
+            Aggregate::Sum | Aggregate::Max => i128::from(config.threshold)
+        };
+        Ok(WindowedCounter {
+            config,
+            window: VecDeque::new(),
+            maxima: VecDeque::new(),
+            sum: 0,
+            aggregates: ConsecutiveCounter::new(threshold)
+        })
+    }
+
+    pub fn handle(mut self, num: Reading) -> WindowedCounter {
+        self.window.push_back(num);
+        self.sum += i128::from(num);
+        while self.maxima.back().is_some_and(|max| *max < num) {
+            self.maxima.pop_back();
+        }
+        self.maxima.push_back(num);
+        if self.window.len() > self.config.size {
+            let oldest = self.window.pop_front().unwrap_or(0);
+            self.sum -= i128::from(oldest);
+            if self.maxima.front() == Some(&oldest) {
+                self.maxima.pop_front();
+            }
+        }
+        if self.window.len() == self.config.size {
+            let aggregate = match self.config.aggregate {
+                Aggregate::Sum | Aggregate::Mean => self.sum,
+                Aggregate::Max => self.maxima.front().map_or(0, |max| i128::from(*max))
+            };
+            self.aggregates = self.aggregates.handle(aggregate);
+        }
+        self
+    }
+
+    pub fn tally(&self) -> Tally {
+        self.aggregates.tally
+    }
 }
 
 #[derive(Debug)]
 struct ConsecutiveCounter {
-    pub current: Option<u32>,
-    pub increases: u32
+    pub current: Option<i128>,
+    pub threshold: i128,
+    pub tally: Tally
 }
 
 impl ConsecutiveCounter {
-    fn new() -> ConsecutiveCounter {
-        ConsecutiveCounter { current: Option::None, increases: 0 }
+    fn new(threshold: i128) -> ConsecutiveCounter {
+        ConsecutiveCounter { current: Option::None, threshold, tally: Tally::default() }
     }
-    fn handle(mut self, num: u32) -> ConsecutiveCounter {
-        let cur = self.current.unwrap_or(num);
-        if num > cur {
-            self.increases += 1;
+    fn handle(mut self, num: i128) -> ConsecutiveCounter {
+        if let Some(cur) = self.current {
+            match num - cur {
+                delta if delta > self.threshold => self.tally.increased += 1,
+                delta if delta < -self.threshold => self.tally.decreased += 1,
+                _ => self.tally.unchanged += 1
+            }
         }
         self.current = Option::Some(num);
         self
@@ -42,7 +294,10 @@ impl ConsecutiveCounter {
 
 #[cfg(test)]
 mod tests {
-    use crate::day_1::{count_increases, count_windowed_increases};
+    use std::io::Cursor;
+    use crate::day_1::{Aggregate, count_increases, count_windowed_increases, Day1, parse_readings, Reading, Readings, Tally, tally_windows, WindowConfig};
+    use crate::parse::{Mode, ParseContext};
+    use crate::solution::StreamingSolution;
 
     #[test]
     fn test_one_increase() {
@@ -50,7 +305,7 @@ mod tests {
             "1\n\
              2\n\
              1\n";
-        let result = count_increases(text);
+        let result = count_increases(parse_readings(text, &mut ParseContext::default()).unwrap()).unwrap();
         assert_eq!(1, result);
     }
 
@@ -60,7 +315,7 @@ mod tests {
             "1\n\
              1\n\
              1\n";
-        let result = count_increases(text);
+        let result = count_increases(parse_readings(text, &mut ParseContext::default()).unwrap()).unwrap();
         assert_eq!(0, result);
     }
 
@@ -70,20 +325,30 @@ mod tests {
             "1\n\
              2\n\
              3\n";
-        let result = count_increases(text);
+        let result = count_increases(parse_readings(text, &mut ParseContext::default()).unwrap()).unwrap();
         assert_eq!(2, result);
     }
 
     #[test]
-    fn get_the_final_count() {
-        let text = include_str!("day1.txt");
-        println!("Day 1 result: {}", count_increases(text));
+    fn test_invalid_reading() {
+        let err = parse_readings("1\n2\nthree\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day1 line 3: invalid number \"three\"", err.to_string());
+    }
+
+    #[test]
+    fn test_lenient_readings() {
+        let mut context = ParseContext::new(Mode::Lenient);
+        let readings = parse_readings("1\n2\n\n3\n4\nfive\n", &mut context).unwrap();
+        assert_eq!(vec![1, 2, 3, 4], readings);
+        assert_eq!(vec![3, 6], context.warnings().iter().map(|warning| warning.line).collect::<Vec<usize>>());
+        assert_eq!(3, count_increases(readings.iter().copied()).unwrap());
+        assert_eq!(1, count_windowed_increases(readings, 3).unwrap());
     }
 
     #[test]
     fn test_empty_window() {
         let text = "";
-        assert_eq!(0, count_windowed_increases(text));
+        assert_eq!(0, count_windowed_increases(parse_readings(text, &mut ParseContext::default()).unwrap(), 3).unwrap());
     }
 
     #[test]
@@ -93,13 +358,125 @@ mod tests {
              1\n\
              1\n\
              2";
-        let result = count_windowed_increases(text);
+        let result = count_windowed_increases(parse_readings(text, &mut ParseContext::default()).unwrap(), 3).unwrap();
         assert_eq!(1, result);
     }
 
     #[test]
-    fn get_part2_count() {
-        let text = include_str!("day1.txt");
-        println!("Day 1 part 2 result: {}", count_windowed_increases(text))
+    fn test_window_sizes() {
+        let readings = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+        assert_eq!(7, count_windowed_increases(readings, 1).unwrap());
+        assert_eq!(5, count_windowed_increases(readings, 3).unwrap());
+        assert_eq!(0, count_windowed_increases(readings, 11).unwrap());
+    }
+
+    #[test]
+    fn test_readings_stream() {
+        let mut context = ParseContext::new(Mode::Lenient);
+        let reader = Cursor::new("199\n200\n\n208\n210\n");
+        let readings = Readings::new(reader, &mut context).collect::<Result<Vec<Reading>, _>>().unwrap();
+        assert_eq!(vec![199, 200, 208, 210], readings);
+        assert_eq!(3, context.warnings()[0].line);
+    }
+
+    #[test]
+    fn test_stream_both_parts() {
+        let mut reader = Cursor::new("199\n200\n208\n210\n200\n207\n240\n269\n260\n263\n");
+        let (part1, part2) = Day1::stream(&mut reader, &mut ParseContext::default()).unwrap();
+        assert_eq!((7, 5), (part1.tally.increased, part2.tally.increased));
+
+        let mut context = ParseContext::default();
+        context.set_option("window", "2");
+        let mut reader = Cursor::new("1\n3\n2\n4\n");
+        let (part1, part2) = Day1::stream(&mut reader, &mut context).unwrap();
+        assert_eq!(("2", "2"), (part1.to_string().as_str(), part2.to_string().as_str()));
+    }
+
+    #[test]
+    fn test_invalid_window_option() {
+        let mut context = ParseContext::default();
+        context.set_option("window", "0");
+        let err = Day1::stream(&mut Cursor::new("1\n"), &mut context).unwrap_err();
+        assert_eq!("invalid value \"0\" for option window: expected a whole number from 1 to 16777216", err.to_string());
+
+        for size in ["18446744073709551615", "100000000000", "16777217"] {
+            context.set_option("window", size);
+            assert!(Day1::stream(&mut Cursor::new("1\n"), &mut context).is_err());
+        }
+        context.set_option("window", "16777216");
+        let (_, part2) = Day1::stream(&mut Cursor::new("1\n2\n"), &mut context).unwrap();
+        assert_eq!("0", part2.to_string());
+
+        let err = tally_windows([1, 2], &WindowConfig::sum(0)).unwrap_err();
+        assert_eq!("invalid value \"0\" for option window: expected a positive whole number", err.to_string());
+    }
+
+    #[test]
+    fn test_tally_categories() {
+        let readings = [1, 3, 3, 2, 2, 5];
+        let tally = tally_windows(readings, &WindowConfig::sum(1)).unwrap();
+        assert_eq!(Tally { increased: 2, decreased: 1, unchanged: 2 }, tally);
+        assert_eq!("2 increased, 1 decreased, 2 unchanged", tally.to_string());
+    }
+
+    #[test]
+    fn test_threshold() {
+        let readings = [10, 11, 13, 12, 8];
+        let config = WindowConfig { threshold: 1, ..WindowConfig::sum(1) };
+        assert_eq!(Tally { increased: 1, decreased: 1, unchanged: 2 }, tally_windows(readings, &config).unwrap());
+    }
+
+    #[test]
+    fn test_mean_aggregate() {
+        let readings = [1, 2, 4, 3, 7];
+        let sum = WindowConfig::sum(2);
+        assert_eq!(Tally { increased: 3, decreased: 0, unchanged: 0 }, tally_windows(readings, &sum).unwrap());
+
+        let mean = WindowConfig { aggregate: Aggregate::Mean, threshold: 1, ..sum };
+        assert_eq!(Tally { increased: 2, decreased: 0, unchanged: 1 }, tally_windows(readings, &mean).unwrap());
+    }
+
+    #[test]
+    fn test_max_aggregate() {
+        let readings = [5, 1, 1, 1, 2, 9, 3, 3, 3];
+        let max = WindowConfig { aggregate: Aggregate::Max, ..WindowConfig::sum(3) };
+        assert_eq!(Tally { increased: 2, decreased: 2, unchanged: 2 }, tally_windows(readings, &max).unwrap());
+    }
+
+    #[test]
+    fn test_counts_option() {
+        let mut context = ParseContext::default();
+        context.set_option("counts", "all");
+        context.set_option("aggregate", "max");
+        let mut reader = Cursor::new("1\n3\n2\n4\n4\n");
+        let (part1, part2) = Day1::stream(&mut reader, &mut context).unwrap();
+        assert_eq!("2 increased, 1 decreased, 1 unchanged", part1.to_string());
+        assert_eq!("1 increased, 0 decreased, 1 unchanged", part2.to_string());
+
+        context.set_option("aggregate", "median");
+        let err = Day1::stream(&mut Cursor::new("1\n"), &mut context).err().unwrap();
+        assert_eq!("invalid value \"median\" for option aggregate: expected sum, mean or max", err.to_string());
+    }
+
+    #[test]
+    fn test_readings_near_limits() {
+        let max = Reading::MAX;
+        let readings = [max - 2, max, max - 1, max, max];
+        assert_eq!(2, count_increases(readings).unwrap());
+        assert_eq!(1, count_windowed_increases(readings, 3).unwrap());
+        assert_eq!(Tally { increased: 0, decreased: 0, unchanged: 1 }, tally_windows(readings, &WindowConfig { threshold: max, ..WindowConfig::sum(4) }).unwrap());
+
+        let mean = WindowConfig { aggregate: Aggregate::Mean, threshold: max, ..WindowConfig::sum(2) };
+        assert_eq!(Tally { increased: 0, decreased: 0, unchanged: 3 }, tally_windows(readings, &mean).unwrap());
+
+        let huge = WindowConfig { size: usize::MAX, ..mean };
+        let err = tally_windows(readings, &huge).unwrap_err();
+        assert_eq!(format!("invalid value \"{}\" for option threshold: too large for a mean over {} readings", max, usize::MAX), err.to_string());
+    }
+
+    #[test]
+    fn test_reading_out_of_range() {
+        let err = parse_readings("18446744073709551615\n18446744073709551616\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day1 line 2: number \"18446744073709551616\" is out of range", err.to_string());
     }
 }
@@ -1,108 +1,528 @@
+use std::fmt::{Display, Formatter};
+use std::iter::{Enumerate, once};
+use std::slice::Iter;
 use std::str::FromStr;
-use crate::day_2::Instruction::{Down, Forward, Up};
+use crate::day_2::Instruction::{Backward, Down, Forward, Hold, Surface, Up};
+use crate::error::{Error, ParseError, ParseErrorKind};
+use crate::parse::{number_error, ParseContext};
+use crate::solution::{Part, Solution};
 
-pub(crate) fn travel(instructions: &str) -> i32 {
-    instructions
-        .lines()
-        .map(perform_part1)
-        .fold(State::new(), |state, delta| state.apply(delta)).value()
+pub struct Day2;
+
+impl Solution for Day2 {
+    type Input = Vec<Instruction>;
+    type Part1 = i128;
+    type Part2 = i128;
+
+    const OPTIONS: &'static [&'static str] = &[];
+
+    fn parse(input: &str, context: &mut ParseContext) -> Result<Self::Input, Error> {
+        Ok(parse_course(input, context)?)
+    }
+
+    fn part1(course: &Self::Input) -> Result<Self::Part1, Error> {
+        travel(course)
+    }
+
+    fn part2(course: &Self::Input) -> Result<Self::Part2, Error> {
+        travel_part2(course)
+    }
 }
 
-pub(crate) fn travel_part2(instructions: &str) -> i32 {
-    instructions
-        .lines()
-        .map(Instruction::from_str)
-        .filter_map(Result::ok)
-        .fold(State::new(), |state, delta| state.apply_part2(delta)).value()
+pub fn parse_course(instructions: &str, context: &mut ParseContext) -> Result<Vec<Instruction>, ParseError> {
+    Ok(parse_course_lines(instructions, context)?.into_iter().map(|(_, instruction)| instruction).collect())
 }
 
-struct State {
-    depth: i32,
-    forward: i32,
-    aim: i32
+// Upper bound on the expanded length of a course, so nested repeats cannot exhaust memory.
+pub const MAX_STEPS: usize = 1 << 24;
+
+// A course is one statement per line: `forward N`, `backward N`, `down N`, `up N`, `surface`,
+// `hold`, or `repeat K { ... }` around further statements. `#` starts a comment.
+// Repeats are expanded, and each instruction is paired with the line it was written on.
+pub fn parse_course_lines(instructions: &str, context: &mut ParseContext) -> Result<Vec<(usize, Instruction)>, ParseError> {
+    let tokens = tokenize(instructions);
+    let mut cursor = Cursor { tokens: &tokens, position: 0 };
+    let mut blocks = vec![Block { count: 1, opener: None, body: Vec::new() }];
+    while let Some(token) = cursor.peek() {
+        match token.kind {
+            TokenKind::End => cursor.advance(),
+            TokenKind::Close => {
+                cursor.advance();
+                if blocks.len() == 1 {
+                    context.recover::<()>(Err(token_error(&token, ParseErrorKind::UnexpectedToken)))?;
+                    continue;
+                }
+                close_block(&mut blocks)?;
+            }
+            _ => match context.recover(statement(&mut cursor))? {
+                Some(Statement::Instruction(line, instruction)) => blocks.last_mut().unwrap().push(line, instruction)?,
+                Some(Statement::Repeat(count, opener)) => blocks.push(Block { count, opener: Some(opener), body: Vec::new() }),
+                // A block opened by a bad statement is still closed by its `}`, but contributes nothing.
+                None => cursor.skip_statement().into_iter()
+                    .for_each(|open| blocks.push(Block { count: 0, opener: Some(open), body: Vec::new() }))
+            }
+        }
+    }
+    while blocks.len() > 1 {
+        let opener = blocks.last().unwrap().opener.unwrap();
+        context.recover::<()>(Err(token_error(&opener, ParseErrorKind::UnclosedBlock)))?;
+        close_block(&mut blocks)?;
+    }
+    Ok(blocks.pop().unwrap().body)
 }
 
-impl State {
-    fn new() -> State {
-        State { depth: 0, forward: 0, aim: 0}
+fn token_error(token: &Token, kind: ParseErrorKind) -> ParseError {
+    ParseError::new(2, token.line, token.column, token.text, kind)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TokenKind {
+    Word,
+    Open,
+    Close,
+    End
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    line: usize,
+    column: usize
+}
+
+// Words are separated by whitespace and braces; every line ends with an End token.
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let token = |kind, text, start: usize| Token { kind, text, line: index + 1, column: start + 1 };
+        let code = line.split('#').next().unwrap_or_default();
+        let mut word_start = None;
+        for (offset, c) in code.char_indices().chain(once((code.len(), ' '))) {
+            let brace = match c {
+                '{' => Some(TokenKind::Open),
+                '}' => Some(TokenKind::Close),
+                _ => None
+            };
+            if c.is_whitespace() || brace.is_some() {
+                if let Some(start) = word_start.take() {
+                    tokens.push(token(TokenKind::Word, &code[start..offset], start));
+                }
+            } else if word_start.is_none() {
+                word_start = Some(offset);
+            }
+            if let Some(kind) = brace {
+                tokens.push(token(kind, &code[offset..offset + 1], offset));
+            }
+        }
+        tokens.push(token(TokenKind::End, "", code.len()));
+    }
+    tokens
+}
+
+struct Cursor<'a> {
+    tokens: &'a [Token<'a>],
+    position: usize
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    // Recovery point after a bad statement: the rest of the line, but not a closing brace.
+    // Returns the opening braces that were skipped.
+    fn skip_statement(&mut self) -> Vec<Token<'a>> {
+        let mut opened = Vec::new();
+        while let Some(token @ Token { kind: TokenKind::Word | TokenKind::Open, .. }) = self.peek() {
+            if token.kind == TokenKind::Open {
+                opened.push(token);
+            }
+            self.advance();
+        }
+        opened
+    }
+}
+
+enum Statement<'a> {
+    Instruction(usize, Instruction),
+    Repeat(u32, Token<'a>)
+}
+
+fn statement<'a>(cursor: &mut Cursor<'a>) -> Result<Statement<'a>, ParseError> {
+    let action = cursor.peek().unwrap_or(Token { kind: TokenKind::End, text: "", line: 1, column: 1 });
+    if action.kind != TokenKind::Word {
+        let kind = if action.kind == TokenKind::End { ParseErrorKind::UnknownAction } else { ParseErrorKind::UnexpectedToken };
+        return Err(token_error(&action, kind));
+    }
+    cursor.advance();
+    let instruction = match action.text {
+        "forward" => Forward(value(cursor, &action)?),
+        "backward" => Backward(value(cursor, &action)?),
+        "down" => Down(value(cursor, &action)?),
+        "up" => Up(value(cursor, &action)?),
+        "surface" => Surface,
+        "hold" => Hold,
+        "repeat" => {
+            let count = value(cursor, &action)?;
+            match cursor.peek() {
+                Some(Token { kind: TokenKind::Open, .. }) => cursor.advance(),
+                _ => return Err(token_error(&action, ParseErrorKind::MissingBlock))
+            }
+            return Ok(Statement::Repeat(count, action));
+        }
+        _ => return Err(token_error(&action, ParseErrorKind::UnknownAction))
+    };
+    match cursor.peek() {
+        Some(token) if token.kind == TokenKind::Word || token.kind == TokenKind::Open =>
+            Err(token_error(&token, ParseErrorKind::UnexpectedToken)),
+        _ => Ok(Statement::Instruction(action.line, instruction))
+    }
+}
+
+fn value(cursor: &mut Cursor, action: &Token) -> Result<u32, ParseError> {
+    match cursor.peek() {
+        Some(token) if token.kind == TokenKind::Word => {
+            cursor.advance();
+            u32::from_str(token.text).map_err(|err| token_error(&token, number_error(&err)))
+        }
+        _ => Err(token_error(action, ParseErrorKind::MissingValue))
+    }
+}
+
+struct Block<'a> {
+    count: u32,
+    opener: Option<Token<'a>>,
+    body: Vec<(usize, Instruction)>
+}
+
+impl Block<'_> {
+    fn push(&mut self, line: usize, instruction: Instruction) -> Result<(), ParseError> {
+        if self.body.len() == MAX_STEPS {
+            return Err(ParseError::new(2, line, 1, "", ParseErrorKind::TooManySteps { max: MAX_STEPS }));
+        }
+        self.body.push((line, instruction));
+        Ok(())
+    }
+}
+
+fn close_block(blocks: &mut Vec<Block>) -> Result<(), ParseError> {
+    let block = blocks.pop().unwrap();
+    let parent = blocks.last_mut().unwrap();
+    let opener = block.opener.unwrap();
+    let expanded = block.body.len().checked_mul(block.count as usize)
+        .and_then(|length| length.checked_add(parent.body.len()));
+    if expanded.is_none_or(|length| length > MAX_STEPS) {
+        return Err(token_error(&opener, ParseErrorKind::TooManySteps { max: MAX_STEPS }));
+    }
+    for _ in 0..block.count {
+        parent.body.extend_from_slice(&block.body);
+    }
+    Ok(())
+}
+
+pub fn travel(instructions: &[Instruction]) -> Result<i128, Error> {
+    navigate(instructions, &Direct)
+}
+
+pub fn travel_part2(instructions: &[Instruction]) -> Result<i128, Error> {
+    navigate(instructions, &Aimed)
+}
+
+pub fn navigate(instructions: &[Instruction], model: &dyn NavigationModel) -> Result<i128, Error> {
+    Ok(summarize(trajectory(instructions, model))?.last.value())
+}
+
+// How an instruction moves the submarine. `step` is 1-based and only used to report overflow.
+pub trait NavigationModel {
+    fn apply(&self, state: State, instruction: &Instruction, step: usize) -> Result<State, Error>;
+}
+
+// Part 1: up and down change depth directly.
+pub struct Direct;
+
+impl NavigationModel for Direct {
+    fn apply(&self, mut state: State, instruction: &Instruction, step: usize) -> Result<State, Error> {
+        match *instruction {
+            Forward(steps) => state.forward = state.forward.checked_add(i64::from(steps)).ok_or(overflow(step, "horizontal position"))?,
+            Backward(steps) => state.forward = state.forward.checked_sub(i64::from(steps)).ok_or(overflow(step, "horizontal position"))?,
+            Down(steps) => state.depth = state.depth.checked_add(i64::from(steps)).ok_or(overflow(step, "depth"))?,
+            Up(steps) => state.depth = state.depth.checked_sub(i64::from(steps)).ok_or(overflow(step, "depth"))?,
+            Surface => state.depth = 0,
+            Hold => {}
+        }
+        Ok(state)
+    }
+}
+
+// Part 2: up and down steer the aim, and moving forward dives along it.
+// Backward retraces a forward move, so it also undoes the depth that move would have gained.
+pub struct Aimed;
+
+impl NavigationModel for Aimed {
+    fn apply(&self, mut state: State, instruction: &Instruction, step: usize) -> Result<State, Error> {
+        match *instruction {
+            Forward(steps) => state.advance(i64::from(steps), step)?,
+            Backward(steps) => state.advance(-i64::from(steps), step)?,
+            Down(steps) => state.aim = state.aim.checked_add(i64::from(steps)).ok_or(overflow(step, "aim"))?,
+            Up(steps) => state.aim = state.aim.checked_sub(i64::from(steps)).ok_or(overflow(step, "aim"))?,
+            Surface => state.depth = 0,
+            Hold => {}
+        }
+        Ok(state)
+    }
+}
+
+pub fn model(part: Part) -> &'static dyn NavigationModel {
+    match part {
+        Part::One => &Direct,
+        Part::Two => &Aimed
+    }
+}
+
+// Walks the course one instruction at a time, yielding the state after each step.
+// Stops after the first overflow.
+pub fn trajectory<'a>(instructions: &'a [Instruction], model: &'a dyn NavigationModel) -> Trajectory<'a> {
+    Trajectory { instructions: instructions.iter().enumerate(), model, state: State::new(), failed: false }
+}
+
+pub struct Trajectory<'a> {
+    instructions: Enumerate<Iter<'a, Instruction>>,
+    model: &'a dyn NavigationModel,
+    state: State,
+    failed: bool
+}
+
+impl Iterator for Trajectory<'_> {
+    type Item = Result<Step, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let (index, instruction) = self.instructions.next()?;
+        let step = index + 1;
+        match self.model.apply(self.state, instruction, step) {
+            Ok(state) => {
+                self.state = state;
+                Some(Ok(Step { step, instruction: *instruction, state }))
+            }
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
     }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Step {
+    pub step: usize,
+    pub instruction: Instruction,
+    pub state: State
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Summary {
+    pub steps: usize,
+    pub max_depth: i64,
+    // Step at which max_depth was first reached; 0 when the course never goes below the surface.
+    pub max_depth_step: usize,
+    pub last: State
+}
+
+pub fn summarize<I: IntoIterator<Item = Result<Step, Error>>>(trajectory: I) -> Result<Summary, Error> {
+    let start = Summary { steps: 0, max_depth: 0, max_depth_step: 0, last: State::new() };
+    trajectory.into_iter().try_fold(start, |mut summary, step| {
+        let step = step?;
+        if step.state.depth > summary.max_depth {
+            summary.max_depth = step.state.depth;
+            summary.max_depth_step = step.step;
+        }
+        summary.steps = step.step;
+        summary.last = step.state;
+        Ok(summary)
+    })
+}
+
+// Optional constraints on a course, checked after every step. Aim is bounded in both directions.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Limits {
+    pub min_depth: Option<i64>,
+    pub max_depth: Option<i64>,
+    pub max_aim: Option<i64>,
+    pub max_horizontal: Option<i64>
+}
+
+impl Limits {
+    pub const OPTIONS: &'static [&'static str] = &["min-depth", "max-depth", "max-aim", "max-horizontal"];
 
-    fn apply(mut self, delta: Delta) -> State {
-        self.depth += delta.delta_depth;
-        self.forward += delta.delta_horizontal;
-        self
+    // Read from `min-depth`, `max-depth`, `max-aim` and `max-horizontal`. Without `min-depth`
+    // the course may not rise above the surface.
+    pub fn from_options(context: &ParseContext) -> Result<Limits, Error> {
+        let limit = |name| context.option(name)
+            .map(|value| value.parse::<i64>().map_err(|_| Error::invalid_option(name, value, "expected a whole number")))
+            .transpose();
+        let max_aim = limit("max-aim")?;
+        if let Some(max) = max_aim.filter(|max| *max < 0) {
+            return Err(Error::invalid_option("max-aim", &max.to_string(), "expected zero or more"));
+        }
+        Ok(Limits {
+            min_depth: Some(limit("min-depth")?.unwrap_or(0)),
+            max_depth: limit("max-depth")?,
+            max_aim,
+            max_horizontal: limit("max-horizontal")?
+        })
     }
 
-    fn apply_part2(mut self, instruction: Instruction) -> State {
-        match instruction {
-            Forward(steps) => {
-                let signed_steps = i32::try_from(steps).unwrap();
-                self.forward += signed_steps;
-                self.depth += signed_steps * self.aim;
+    fn check(&self, state: &State) -> Vec<(&'static str, Bound, i64, i64)> {
+        let checks = [
+            ("depth", state.depth, self.min_depth, self.max_depth),
+            ("aim", state.aim, self.max_aim.map(|max| -max), self.max_aim),
+            ("horizontal position", state.forward, None, self.max_horizontal)
+        ];
+        let mut failed = Vec::new();
+        for (quantity, value, min, max) in checks {
+            if let Some(min) = min.filter(|min| value < *min) {
+                failed.push((quantity, Bound::Below, value, min));
+            }
+            if let Some(max) = max.filter(|max| value > *max) {
+                failed.push((quantity, Bound::Above, value, max));
             }
-            Down(steps) => self.aim += i32::try_from(steps).unwrap(),
-            Up(steps) => self.aim -= i32::try_from(steps).unwrap()
         }
-        self
+        failed
     }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bound {
+    Below,
+    Above
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Violation {
+    pub step: usize,
+    pub line: usize,
+    pub instruction: Instruction,
+    pub quantity: &'static str,
+    pub value: i64,
+    pub bound: Bound,
+    pub limit: i64
+}
 
-    fn value(&self) -> i32 {
-        self.depth * self.forward
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let bound = match self.bound {
+            Bound::Below => "below the minimum",
+            Bound::Above => "above the maximum"
+        };
+        write!(f, "line {} step {} ({}): {} {} is {} {}", self.line, self.step, self.instruction, self.quantity, self.value, bound, self.limit)
     }
 }
 
-struct Delta {
-    pub delta_depth: i32,
-    pub delta_horizontal: i32
+// Reports every step that breaks a limit. The answers themselves never depend on the limits.
+pub fn validate(course: &[(usize, Instruction)], model: &dyn NavigationModel, limits: &Limits) -> Result<Vec<Violation>, Error> {
+    let instructions = course.iter().map(|(_, instruction)| *instruction).collect::<Vec<Instruction>>();
+    let mut violations = Vec::new();
+    for step in trajectory(&instructions, model) {
+        let step = step?;
+        let line = course[step.step - 1].0;
+        violations.extend(limits.check(&step.state).into_iter().map(|(quantity, bound, value, limit)|
+            Violation { step: step.step, line, instruction: step.instruction, quantity, value, bound, limit }));
+    }
+    Ok(violations)
+}
+
+fn overflow(step: usize, quantity: &'static str) -> Error {
+    Error::Overflow { day: 2, step, quantity }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct State {
+    pub depth: i64,
+    pub forward: i64,
+    pub aim: i64
 }
 
-fn perform_part1(instruction: &str) -> Delta {
-    let parsed: Instruction = Instruction::from_str(instruction).unwrap();
-    fn to_signed(unsigned: u32) -> i32 {
-        i32::try_from(unsigned).unwrap()
+impl State {
+    pub fn new() -> State {
+        State { depth: 0, forward: 0, aim: 0}
+    }
+
+    fn advance(&mut self, steps: i64, step: usize) -> Result<(), Error> {
+        self.forward = self.forward.checked_add(steps).ok_or(overflow(step, "horizontal position"))?;
+        self.depth = steps.checked_mul(self.aim)
+            .and_then(|change| self.depth.checked_add(change))
+            .ok_or(overflow(step, "depth"))?;
+        Ok(())
     }
-    match parsed {
-        Forward(steps) => Delta { delta_depth: 0, delta_horizontal: to_signed(steps)},
-        Down(steps) => Delta { delta_depth: to_signed(steps), delta_horizontal: 0},
-        Up(steps) => Delta { delta_depth: -to_signed(steps), delta_horizontal: 0}
+
+    // Positions are checked i64, so their product always fits in an i128.
+    pub fn value(&self) -> i128 {
+        i128::from(self.depth) * i128::from(self.forward)
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 #[derive(Debug)]
-enum Instruction {
+pub enum Instruction {
     Forward(u32),
+    Backward(u32),
     Down(u32),
-    Up(u32)
+    Up(u32),
+    Surface,
+    Hold
 }
 
+// Parses a single instruction; blocks only make sense within a course.
 impl FromStr for Instruction {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut split = s.split(' ');
-        let action = split.next().unwrap();
-        let value = split.next().unwrap();
-        match action {
-            "forward" => Ok(Forward(u32::from_str(value).unwrap())),
-            "up" => Ok(Up(u32::from_str(value).unwrap())),
-            "down" => Ok(Down(u32::from_str(value).unwrap())),
-            _ => Err(String::from("Incorrect action"))
+        let tokens = tokenize(s);
+        let mut cursor = Cursor { tokens: &tokens, position: 0 };
+        match statement(&mut cursor)? {
+            Statement::Instruction(_, instruction) => match cursor.peek() {
+                Some(token) if token.kind == TokenKind::Close => Err(token_error(&token, ParseErrorKind::UnexpectedToken)),
+                _ => Ok(instruction)
+            },
+            Statement::Repeat(_, action) => Err(token_error(&action, ParseErrorKind::UnknownAction))
         }
     }
 }
 
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Forward(steps) => write!(f, "forward {}", steps),
+            Backward(steps) => write!(f, "backward {}", steps),
+            Down(steps) => write!(f, "down {}", steps),
+            Up(steps) => write!(f, "up {}", steps),
+            Surface => write!(f, "surface"),
+            Hold => write!(f, "hold")
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
-    use crate::day_2::Instruction::{Forward, Up};
-    use crate::day_2::{Instruction, travel, travel_part2};
+    use crate::day_2::Instruction::{Backward, Down, Forward, Hold, Surface, Up};
+    use crate::day_2::{Aimed, Bound, Direct, Instruction, Limits, MAX_STEPS, model, navigate, NavigationModel, parse_course, parse_course_lines, State, Step,
+                       summarize, trajectory, travel, travel_part2, validate};
+    use crate::error::{Error, ParseErrorKind};
+    use crate::parse::{Mode, ParseContext};
+    use crate::solution::Part;
 
     #[test]
     fn test_example() {
-        let example_input = include_str!("day2_example.txt");
-        assert_eq!(150, travel(example_input));
+        let example_input = include_str!("../examples/day2_example.txt");
+        assert_eq!(Ok(150), travel(&parse_course(example_input, &mut ParseContext::default()).unwrap()));
     }
 
     #[test]
@@ -116,20 +536,203 @@ mod tests {
     }
 
     #[test]
-    fn test_answer() {
-        let input = include_str!("day2.txt");
-        println!("Day 2 {}", travel(input));
+    fn test_parse_errors() {
+        assert_eq!("day2 line 1: unknown action \"sideways\"", Instruction::from_str("sideways 3").unwrap_err().to_string());
+        assert_eq!("day2 line 1: missing value after \"down\"", Instruction::from_str("down").unwrap_err().to_string());
+        assert_eq!("day2 line 1: invalid number \"-4\"", Instruction::from_str("up -4").unwrap_err().to_string());
+        assert_eq!("day2 line 1: unexpected \"now\" at column 11", Instruction::from_str("forward 1 now").unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_parse_course_reports_line() {
+        let err = parse_course("forward 5\ndown 5\nforward x\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!((3, 9), (err.line, err.column));
+    }
+
+    #[test]
+    fn test_lenient_course() {
+        let mut context = ParseContext::new(Mode::Lenient);
+        let course = parse_course("forward 5\nsideways 2\ndown 5\n", &mut context).unwrap();
+        assert_eq!(vec![Forward(5), Down(5)], course);
+        assert_eq!(2, context.warnings()[0].line);
     }
 
     #[test]
     fn example_with_aim() {
-        let example_input = include_str!("day2_example.txt");
-        assert_eq!(900, travel_part2(example_input));
+        let example_input = include_str!("../examples/day2_example.txt");
+        assert_eq!(Ok(900), travel_part2(&parse_course(example_input, &mut ParseContext::default()).unwrap()));
+    }
+
+    #[test]
+    fn test_large_course_does_not_wrap() {
+        let max = i128::from(u32::MAX);
+        assert_eq!(Ok(max * max), travel(&[Forward(u32::MAX), Down(u32::MAX)]));
+
+        let course = vec![Forward(u32::MAX), Down(u32::MAX), Forward(u32::MAX)];
+        let err = travel_part2(&course).unwrap_err();
+        assert_eq!("day2 step 3: depth overflowed", err.to_string());
+    }
+
+    #[test]
+    fn test_state_overflow() {
+        let state = State { depth: i64::MAX - 1, forward: 0, aim: i64::MIN + 1 };
+        let err = Direct.apply(state, &Down(2), 4).err().unwrap();
+        assert_eq!(Error::Overflow { day: 2, step: 4, quantity: "depth" }, err);
+
+        let state = State { depth: 0, forward: 0, aim: i64::MIN + 1 };
+        let err = Aimed.apply(state, &Up(2), 7).err().unwrap();
+        assert_eq!(Error::Overflow { day: 2, step: 7, quantity: "aim" }, err);
+    }
+
+    #[test]
+    fn test_value_out_of_range() {
+        let err = Instruction::from_str("forward 4294967296").unwrap_err();
+        assert_eq!("day2 line 1: number \"4294967296\" is out of range", err.to_string());
+    }
+
+    #[test]
+    fn test_trajectory() {
+        let course = parse_course(include_str!("../examples/day2_example.txt"), &mut ParseContext::default()).unwrap();
+        let steps = trajectory(&course, &Aimed).collect::<Result<Vec<Step>, Error>>().unwrap();
+        assert_eq!(6, steps.len());
+        assert_eq!(Step { step: 1, instruction: Forward(5), state: State { depth: 0, forward: 5, aim: 0 } }, steps[0]);
+        assert_eq!(Step { step: 4, instruction: Up(3), state: State { depth: 40, forward: 13, aim: 2 } }, steps[3]);
+        assert_eq!(State { depth: 60, forward: 15, aim: 10 }, steps[5].state);
+
+        let depths = trajectory(&course, &Direct).map(|step| step.unwrap().state.depth).collect::<Vec<i64>>();
+        assert_eq!(vec![0, 5, 5, 2, 10, 10], depths);
+    }
+
+    #[test]
+    fn test_summary() {
+        let course = vec![Down(4), Up(1), Down(2), Up(5)];
+        let summary = summarize(trajectory(&course, &Direct)).unwrap();
+        assert_eq!((4, 5, 3, 0), (summary.steps, summary.max_depth, summary.max_depth_step, summary.last.depth));
+
+        let surface = summarize(trajectory(&[Up(3), Forward(1)], &Direct)).unwrap();
+        assert_eq!((0, 0), (surface.max_depth, surface.max_depth_step));
+        assert_eq!(State::new(), summarize(trajectory(&[], &Aimed)).unwrap().last);
+    }
+
+    #[test]
+    fn test_trajectory_stops_at_overflow() {
+        let course = vec![Forward(u32::MAX), Down(u32::MAX), Forward(u32::MAX), Forward(1)];
+        let steps = trajectory(&course, &Aimed).collect::<Vec<Result<Step, Error>>>();
+        assert_eq!(3, steps.len());
+        assert_eq!(Err(Error::Overflow { day: 2, step: 3, quantity: "depth" }), steps[2]);
+    }
+
+    #[test]
+    fn test_parse_extended_instructions() {
+        assert_eq!(Backward(4), Instruction::from_str("backward 4").unwrap());
+        assert_eq!(Surface, Instruction::from_str("  surface # come up for air").unwrap());
+        assert_eq!(Hold, Instruction::from_str("hold").unwrap());
+        assert_eq!("day2 line 1: unexpected \"3\" at column 6", Instruction::from_str("hold 3").unwrap_err().to_string());
+        assert_eq!("day2 line 1: unknown action \"repeat\"", Instruction::from_str("repeat 2 {").unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_repeat_blocks() {
+        let input = "# survey run\nrepeat 2 {\n    forward 1\n    repeat 3 { down 1 }\n}\n\nhold\nrepeat 0 {\n  up 9\n}\n";
+        let course = parse_course_lines(input, &mut ParseContext::default()).unwrap();
+        let expected = vec![(3, Forward(1)), (4, Down(1)), (4, Down(1)), (4, Down(1)),
+                            (3, Forward(1)), (4, Down(1)), (4, Down(1)), (4, Down(1)), (7, Hold)];
+        assert_eq!(expected, course);
+    }
+
+    #[test]
+    fn test_block_errors() {
+        let parse = |input| parse_course(input, &mut ParseContext::default()).unwrap_err().to_string();
+        assert_eq!("day2 line 1: expected \"{\" after \"repeat\"", parse("repeat 3\n{ forward 1 }"));
+        assert_eq!("day2 line 2: \"repeat\" block is never closed", parse("forward 1\nrepeat 3 {\nforward 1"));
+        assert_eq!("day2 line 2: unexpected \"}\" at column 1", parse("forward 1\n}"));
+        assert_eq!("day2 line 1: missing value after \"forward\"", parse("repeat 2 { forward }"));
+        assert_eq!("day2 line 1: invalid number \"x\"", parse("repeat x { forward 1 }"));
+
+        let err = parse_course("repeat 65536 { repeat 65536 { hold } }", &mut ParseContext::default()).unwrap_err();
+        assert_eq!(ParseErrorKind::TooManySteps { max: MAX_STEPS }, err.kind);
+    }
+
+    #[test]
+    fn test_lenient_blocks() {
+        let mut context = ParseContext::new(Mode::Lenient);
+        let course = parse_course("repeat 2 { forward 1 sideways }\nrepeat 2 {\n  sideways 3\n  down 1\n", &mut context).unwrap();
+        assert_eq!(vec![Down(1), Down(1)], course);
+        let lines = context.warnings().iter().map(|warning| warning.line).collect::<Vec<usize>>();
+        assert_eq!(vec![1, 3, 2], lines);
+
+        let mut context = ParseContext::new(Mode::Lenient);
+        let course = parse_course("repeat 2 {\n repeat x {\n forward 1\n }\n down 1\n}\n", &mut context).unwrap();
+        assert_eq!(vec![Down(1), Down(1)], course);
+        assert_eq!(vec![(2, ParseErrorKind::InvalidNumber)], context.warnings().iter().map(|warning| (warning.line, warning.kind.clone())).collect::<Vec<(usize, ParseErrorKind)>>());
+
+        let mut context = ParseContext::new(Mode::Lenient);
+        let course = parse_course("forward 1 {\n down 4\n", &mut context).unwrap();
+        assert!(course.is_empty());
+        assert_eq!(vec![ParseErrorKind::UnexpectedToken, ParseErrorKind::UnclosedBlock], context.warnings().iter().map(|warning| warning.kind.clone()).collect::<Vec<ParseErrorKind>>());
+    }
+
+    #[test]
+    fn test_extended_navigation() {
+        let course = vec![Down(5), Forward(4), Backward(1), Hold, Surface, Forward(1)];
+        let part1 = summarize(trajectory(&course, &Direct)).unwrap().last;
+        assert_eq!(State { depth: 0, forward: 4, aim: 0 }, part1);
+        let part2 = summarize(trajectory(&course, &Aimed)).unwrap().last;
+        assert_eq!(State { depth: 5, forward: 4, aim: 5 }, part2);
+        assert_eq!(Ok(45), travel_part2(&[Down(5), Forward(4), Backward(1)]));
+    }
+
+    #[test]
+    fn test_validate() {
+        let course = parse_course_lines("down 2\nrepeat 2 {\n  forward 3\n  up 2\n}\nsurface\n", &mut ParseContext::default()).unwrap();
+        let limits = Limits { min_depth: Some(0), max_depth: None, max_aim: Some(1), max_horizontal: Some(5) };
+
+        let part1 = validate(&course, &Direct, &limits).unwrap();
+        let found = part1.iter().map(|violation| (violation.step, violation.line, violation.quantity)).collect::<Vec<(usize, usize, &str)>>();
+        assert_eq!(vec![(4, 3, "horizontal position"), (5, 4, "depth"), (5, 4, "horizontal position"), (6, 6, "horizontal position")], found);
+        assert_eq!("line 4 step 5 (up 2): depth -2 is below the minimum 0", part1[1].to_string());
+
+        let part2 = validate(&course, &Aimed, &limits).unwrap();
+        assert_eq!(Bound::Above, part2[0].bound);
+        assert_eq!("line 1 step 1 (down 2): aim 2 is above the maximum 1", part2[0].to_string());
+        let below = part2.iter().find(|violation| violation.bound == Bound::Below).unwrap();
+        assert_eq!("line 4 step 5 (up 2): aim -2 is below the minimum -1", below.to_string());
+
+        assert!(validate(&course, &Aimed, &Limits::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_limits_from_options() {
+        let mut context = ParseContext::default();
+        assert_eq!(Ok(Limits { min_depth: Some(0), ..Limits::default() }), Limits::from_options(&context));
+        context.set_option("min-depth", "-10");
+        context.set_option("max-aim", "40");
+        assert_eq!(Ok(Limits { min_depth: Some(-10), max_aim: Some(40), ..Limits::default() }), Limits::from_options(&context));
+        context.set_option("max-aim", "-1");
+        assert_eq!("invalid value \"-1\" for option max-aim: expected zero or more", Limits::from_options(&context).unwrap_err().to_string());
+        context.set_option("max-aim", "40");
+        context.set_option("max-depth", "deep");
+        assert_eq!("invalid value \"deep\" for option max-depth: expected a whole number", Limits::from_options(&context).unwrap_err().to_string());
+    }
+
+    // Aimed navigation where a current pushes the submarine down by a fixed amount on every forward move.
+    struct Drift(i64);
+
+    impl NavigationModel for Drift {
+        fn apply(&self, state: State, instruction: &Instruction, step: usize) -> Result<State, Error> {
+            let mut state = Aimed.apply(state, instruction, step)?;
+            if let Forward(_) = instruction {
+                state.depth += self.0;
+            }
+            Ok(state)
+        }
     }
 
     #[test]
-    fn test_with_aim() {
-        let input = include_str!("day2.txt");
-        println!("Day2 part 2: {}", travel_part2(input));
+    fn test_custom_model() {
+        let course = parse_course(include_str!("../examples/day2_example.txt"), &mut ParseContext::default()).unwrap();
+        assert_eq!(Ok(150), navigate(&course, model(Part::One)));
+        assert_eq!(Ok(900), navigate(&course, model(Part::Two)));
+        assert_eq!(Ok(15 * 63), navigate(&course, &Drift(1)));
     }
 }
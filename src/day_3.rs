@@ -1,204 +1,749 @@
-fn transpose<T>(matrix: &[Vec<T>]) -> Vec<Vec<T>> where T: Clone {
-    if matrix.is_empty() {
-        return Vec::new()
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+use crate::error::{Error, ParseError, ParseErrorKind};
+use crate::parse::ParseContext;
+use crate::solution::Solution;
+
+// Rows are packed into a u64, so ratings and their products always fit in a u128.
+const MAX_WIDTH: usize = 64;
+
+pub struct Day3;
+
+pub struct Diagnostic {
+    pub report: Report,
+    pub settings: Settings
+}
+
+impl Solution for Day3 {
+    type Input = Diagnostic;
+    type Part1 = u128;
+    type Part2 = u128;
+
+    const OPTIONS: &'static [&'static str] = Settings::OPTIONS;
+
+    fn parse(input: &str, context: &mut ParseContext) -> Result<Self::Input, Error> {
+        let settings = Settings::from_options(context)?;
+        Ok(Diagnostic { report: read_report(input, settings.width, context)?, settings })
+    }
+
+    fn part1(diagnostic: &Self::Input) -> Result<Self::Part1, Error> {
+        Ok(power_consumption(&diagnostic.report))
+    }
+
+    fn part2(diagnostic: &Self::Input) -> Result<Self::Part2, Error> {
+        life_support_rating(&diagnostic.report, &diagnostic.settings)
     }
-    (0..matrix[0].len())
-        .map(|i| matrix.iter().map(|row| row[i].clone()).collect::<Vec<T>>())
-        .collect()
 }
 
-fn mean(vec: &[u8]) -> u8 {
-    let total: u32 = vec.iter().map(|n| *n as u32).sum();
-    let float_mean = f64::from(total) / vec.len() as f64;
-    u8::try_from(float_mean.round() as i32).unwrap()
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub oxygen: Criterion<'static>,
+    pub co2: Criterion<'static>,
+    pub width: Option<usize>
 }
 
-fn gamma(input: &str) -> Vec<u8> {
-    means(&read_input(input))
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { oxygen: Criterion::OXYGEN, co2: Criterion::CO2, width: None }
+    }
 }
 
-fn means(matrix: &[Vec<u8>]) -> Vec<u8> {
-    let transposed = transpose(matrix);
-    transposed.iter().map(|column| mean(column)).collect()
+impl Settings {
+    pub const OPTIONS: &'static [&'static str] = &["oxygen-tie", "co2-tie", "width"];
+
+    pub fn from_options(context: &ParseContext) -> Result<Settings, Error> {
+        let tie = |name, default| match context.option(name) {
+            None => Ok(default),
+            Some(value) => TiePolicy::from_str(value)
+                .map_err(|_| Error::invalid_option(name, value, "expected one, zero or error"))
+        };
+        let width = context.option("width")
+            .map(|value| value.parse::<usize>().ok()
+                .filter(|width| (1..=MAX_WIDTH).contains(width))
+                .ok_or_else(|| Error::invalid_option("width", value, &format!("expected a whole number from 1 to {}", MAX_WIDTH))))
+            .transpose()?;
+        Ok(Settings {
+            oxygen: Criterion { tie: tie("oxygen-tie", Criterion::OXYGEN.tie)?, ..Criterion::OXYGEN },
+            co2: Criterion { tie: tie("co2-tie", Criterion::CO2.tie)?, ..Criterion::CO2 },
+            width
+        })
+    }
 }
 
-fn read_input(input: &str) -> Vec<Vec<u8>> {
-    input
-        .lines()
-        .map(|line| line.split("").filter_map(|char| char.parse::<u8>().ok()).collect::<Vec<u8>>())
-        .collect()
+// Which bit survives when a position splits the remaining rows evenly.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TiePolicy {
+    PreferOne,
+    PreferZero,
+    Error
 }
 
-fn epsilon(input: &[u8]) -> Vec<u8> {
-    input.iter().map(|n| (n + 1) % 2).collect()
+impl FromStr for TiePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "one" => Ok(TiePolicy::PreferOne),
+            "zero" => Ok(TiePolicy::PreferZero),
+            "error" => Ok(TiePolicy::Error),
+            _ => Err(())
+        }
+    }
 }
 
-fn binary_string_to_int(input: &[u8]) -> u32 {
-    let vec_of_strings = input.iter().map(|number| number.to_string()).collect::<Vec<String>>();
-    let string_representation = vec_of_strings.join("");
-    u32::from_str_radix(&string_representation, 2).unwrap()
+// The column a strategy decides on: one position, restricted to the rows still in the running.
+pub struct Column<'a> {
+    pub position: usize,
+    pub report: &'a Report,
+    pub survivors: &'a RowSet
 }
 
-pub(crate) fn power_consumption(input: &str) -> u32 {
-    let gamma_vec = gamma(input);
-    let epsilon_vec = epsilon(&gamma_vec);
-    binary_string_to_int(&gamma_vec) * binary_string_to_int(&epsilon_vec)
+impl Column<'_> {
+    pub fn ones(&self) -> usize {
+        self.report.ones(self.position, self.survivors)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.survivors.len()
+    }
 }
 
-struct OxygenIterator {
-    curr_position: usize,
-    matrix: Vec<Vec<u8>>,
-    flip: u8
+// Picks the bit to keep at a position, or None when the column is tied.
+pub trait Strategy: Debug {
+    fn select(&self, column: &Column) -> Option<bool>;
 }
 
-impl OxygenIterator {
-    fn new(matrix: Vec<Vec<u8>>) -> OxygenIterator {
-        OxygenIterator { curr_position: 0, flip: 0, matrix }
+#[derive(Debug)]
+pub struct MostCommon;
+
+impl Strategy for MostCommon {
+    fn select(&self, column: &Column) -> Option<bool> {
+        let (ones, zeros) = (column.ones(), column.remaining() - column.ones());
+        (ones != zeros).then_some(ones > zeros)
     }
+}
+
+#[derive(Debug)]
+pub struct LeastCommon;
+
+impl Strategy for LeastCommon {
+    fn select(&self, column: &Column) -> Option<bool> {
+        MostCommon.select(column).map(|bit| !bit)
+    }
+}
+
+// Follows the bits of a fixed value, as wide as the report, so it never ties.
+#[derive(Debug)]
+pub struct FixedMask(pub u64);
+
+impl Strategy for FixedMask {
+    fn select(&self, column: &Column) -> Option<bool> {
+        Some(self.0 >> (column.report.width() - 1 - column.position) & 1 == 1)
+    }
+}
+
+// Most common bit when each row counts with its weight, indexed like the report's rows.
+#[derive(Debug)]
+pub struct Weighted(pub Vec<u64>);
+
+impl Strategy for Weighted {
+    fn select(&self, column: &Column) -> Option<bool> {
+        let (ones, zeros) = column.survivors.iter().fold((0u128, 0u128), |(ones, zeros), row| {
+            let weight = u128::from(self.0.get(row).copied().unwrap_or(0));
+            match column.report.column(column.position).contains(row) {
+                true => (ones + weight, zeros),
+                false => (ones, zeros + weight)
+            }
+        });
+        (ones != zeros).then_some(ones > zeros)
+    }
+}
+
+// A named strategy with the tie policy that settles the positions it cannot decide.
+#[derive(Clone, Copy, Debug)]
+pub struct Criterion<'a> {
+    pub name: &'static str,
+    pub strategy: &'a dyn Strategy,
+    pub tie: TiePolicy
+}
+
+impl Criterion<'_> {
+    pub const OXYGEN: Criterion<'static> = Criterion { name: "oxygen generator rating", strategy: &MostCommon, tie: TiePolicy::PreferOne };
+    pub const CO2: Criterion<'static> = Criterion { name: "CO2 scrubber rating", strategy: &LeastCommon, tie: TiePolicy::PreferZero };
+
+    fn break_tie(&self, position: usize) -> Result<bool, Error> {
+        match self.tie {
+            TiePolicy::PreferOne => Ok(true),
+            TiePolicy::PreferZero => Ok(false),
+            TiePolicy::Error => Err(Error::Tie { day: 3, position, quantity: self.name })
+        }
+    }
+}
 
-    fn negating(matrix: Vec<Vec<u8>>) -> OxygenIterator {
-        OxygenIterator { curr_position: 0, flip: 1, matrix }
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Unique(u64),
+    // Every position was used and several identical rows are left.
+    Duplicates { value: u64, count: usize },
+    Empty
+}
+
+// One narrowing step: how many rows were left, how many had a 1, and which bit was kept.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FilterStep {
+    pub position: usize,
+    pub remaining: usize,
+    pub ones: usize,
+    pub kept: bool,
+    pub tie: bool
+}
+
+impl FilterStep {
+    pub fn survivors(&self) -> usize {
+        if self.kept { self.ones } else { self.remaining - self.ones }
     }
+}
 
-    fn filter(&mut self) {
-        let to_keep = bit_to_keep(&self.matrix, self.curr_position);
-        let to_keep = (to_keep + self.flip) % 2;
-        let new_matrix: Vec<Vec<u8>> = self.matrix.iter()
-            .filter(|row| row[self.curr_position] == to_keep)
-            // .map(|vec| vec.clone())
-            .cloned()
-            .collect::<Vec<Vec<u8>>>();
-        self.matrix = new_matrix;
-        self.curr_position += 1;
+impl Display for FilterStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "position {}: {} of {} rows have a 1, kept {}{}, {} left",
+               self.position, self.ones, self.remaining, u8::from(self.kept), if self.tie { " on a tie" } else { "" }, self.survivors())
     }
 }
 
-impl Iterator for OxygenIterator {
-    type Item = Vec<Vec<u8>>;
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rating {
+    pub outcome: Outcome,
+    pub trace: Vec<FilterStep>
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.matrix.len() > 1 {
-            self.filter();
-            Option::Some(self.matrix.clone())
-        } else {
-            Option::None
+impl Outcome {
+    pub fn value(&self) -> Option<u64> {
+        match *self {
+            Outcome::Unique(value) | Outcome::Duplicates { value, .. } => Some(value),
+            Outcome::Empty => None
         }
     }
 }
 
-fn oxygen_generator_rating(input: &str) -> u32 {
-    let matrix = read_input(input);
-    let iterator = OxygenIterator::new(matrix);
-    calculate_rating(iterator)
+impl Rating {
+    pub fn value(&self) -> Option<u64> {
+        self.outcome.value()
+    }
+
+    // Positions where the tie policy decided which rows to keep.
+    pub fn ties(&self) -> Vec<usize> {
+        self.trace.iter().filter(|step| step.tie).map(|step| step.position).collect()
+    }
 }
 
-fn co2_scrubber_rating(input: &str) -> u32 {
-    let matrix = read_input(input);
-    let iterator = OxygenIterator::negating(matrix);
-    calculate_rating(iterator)
+// A diagnostic report packed one row per u64, with position 0 the leftmost (most significant) bit.
+// Each column is also kept as the set of rows with a 1 there, so counting is a popcount.
+#[derive(PartialEq, Debug)]
+pub struct Report {
+    width: usize,
+    rows: Vec<u64>,
+    columns: Vec<RowSet>
 }
 
-fn calculate_rating(iterator: OxygenIterator) -> u32 {
-    let last_iteration = iterator.last().unwrap();
-    let remaining_value = last_iteration.first().unwrap();
-    let owned = remaining_value.to_vec();
-    binary_string_to_int(&owned)
+impl Report {
+    // None when the width is over 64 or a row has bits set beyond the width.
+    pub fn new(width: usize, rows: Vec<u64>) -> Option<Report> {
+        if width > MAX_WIDTH || rows.iter().any(|row| row & !mask(width) != 0) {
+            return None;
+        }
+        Some(Report::packed(width, rows))
+    }
+
+    fn packed(width: usize, rows: Vec<u64>) -> Report {
+        let mut columns = vec![RowSet::empty(rows.len()); width];
+        for (index, row) in rows.iter().enumerate() {
+            for (position, column) in columns.iter_mut().enumerate() {
+                if row >> (width - 1 - position) & 1 == 1 {
+                    column.insert(index);
+                }
+            }
+        }
+        Report { width, rows, columns }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn rows(&self) -> &[u64] {
+        &self.rows
+    }
+
+    pub fn all_rows(&self) -> RowSet {
+        RowSet::full(self.rows.len())
+    }
+
+    pub fn column(&self, position: usize) -> &RowSet {
+        &self.columns[position]
+    }
+
+    // Number of rows in `among` with a 1 at `position`.
+    pub fn ones(&self, position: usize, among: &RowSet) -> usize {
+        self.columns[position].count_common(among)
+    }
+
+    fn mask(&self) -> u64 {
+        mask(self.width)
+    }
 }
 
-pub(crate) fn life_support_rating(input: &str) -> u32 {
-    oxygen_generator_rating(input) * co2_scrubber_rating(input)
+fn mask(width: usize) -> u64 {
+    if width == 0 { 0 } else { u64::MAX >> (64 - width) }
 }
 
-fn bit_to_keep(matrix: &[Vec<u8>], position: usize) -> u8 {
-    let row_means = means(matrix);
-    row_means[position]
+// A set of row indices, one bit per row.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RowSet {
+    words: Vec<u64>
+}
+
+impl RowSet {
+    fn empty(rows: usize) -> RowSet {
+        RowSet { words: vec![0; rows.div_ceil(64)] }
+    }
+
+    fn full(rows: usize) -> RowSet {
+        let mut set = RowSet { words: vec![u64::MAX; rows / 64] };
+        if !rows.is_multiple_of(64) {
+            set.words.push((1 << (rows % 64)) - 1);
+        }
+        set
+    }
+
+    fn insert(&mut self, row: usize) {
+        self.words[row / 64] |= 1 << (row % 64);
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        self.words.get(row / 64).is_some_and(|word| word >> (row % 64) & 1 == 1)
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.words.iter().position(|word| *word != 0)
+            .map(|index| index * 64 + self.words[index].trailing_zeros() as usize)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, word)| {
+            (0..64).filter(move |bit| word >> bit & 1 == 1).map(move |bit| index * 64 + bit)
+        })
+    }
+
+    fn count_common(&self, other: &RowSet) -> usize {
+        self.words.iter().zip(&other.words).map(|(a, b)| (a & b).count_ones() as usize).sum()
+    }
+
+    // Keeps the rows that have `bit` in `column`.
+    fn retain(&mut self, column: &RowSet, bit: bool) {
+        for (word, column) in self.words.iter_mut().zip(&column.words) {
+            *word &= if bit { *column } else { !*column };
+        }
+    }
+}
+
+pub fn read_input(input: &str, context: &mut ParseContext) -> Result<Report, ParseError> {
+    read_report(input, None, context)
+}
+
+// Every line must be as wide as the first one. With a declared width, shorter lines are
+// taken to have lost their leading zeros, and only longer or blank ones are rejected. A declared
+// width is capped at 64.
+pub fn read_report(input: &str, declared: Option<usize>, context: &mut ParseContext) -> Result<Report, ParseError> {
+    let declared = declared.map(|width| width.min(MAX_WIDTH));
+    let mut width = declared;
+    let mut rows = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let row = read_line(line, declared.unwrap_or(MAX_WIDTH)).and_then(|(found, row)| {
+            match (width, declared) {
+                (None, _) => width = Some(found),
+                (Some(expected), None) if found != expected =>
+                    return Err(ParseError::new(3, 1, 1, line, ParseErrorKind::WrongWidth { expected, found })),
+                _ => {}
+            }
+            Ok(row)
+        });
+        if let Some(row) = context.recover(row.map_err(|err| err.on_line(index + 1)))? {
+            rows.push(row);
+        }
+    }
+    Ok(Report::packed(width.unwrap_or(0), rows))
+}
+
+fn read_line(line: &str, max: usize) -> Result<(usize, u64), ParseError> {
+    if line.is_empty() {
+        return Err(ParseError::new(3, 1, 1, line, ParseErrorKind::EmptyRow));
+    }
+    if line.len() > max {
+        return Err(ParseError::new(3, 1, max + 1, line, ParseErrorKind::TooWide { max }));
+    }
+    let row = line.char_indices().try_fold(0, |row, (index, char)| match char {
+        '0' => Ok(row << 1),
+        '1' => Ok(row << 1 | 1),
+        _ => Err(ParseError::new(3, 1, index + 1, &line[index..index + char.len_utf8()], ParseErrorKind::InvalidDigit))
+    })?;
+    Ok((line.len(), row))
+}
+
+// Most common bit in each column, with ties going to 1.
+fn gamma(report: &Report) -> u64 {
+    let all = report.all_rows();
+    (0..report.width()).fold(0, |gamma, position| gamma << 1 | u64::from(2 * report.ones(position, &all) >= all.len()))
+}
+
+fn epsilon(report: &Report, gamma: u64) -> u64 {
+    !gamma & report.mask()
+}
+
+pub fn power_consumption(report: &Report) -> u128 {
+    let gamma = gamma(report);
+    u128::from(gamma) * u128::from(epsilon(report, gamma))
+}
+
+// Narrows the surviving rows one position at a time until at most one is left. Survivors are a
+// row set over the packed report, so no rows are copied while filtering.
+pub fn rating(report: &Report, criterion: &Criterion) -> Result<Rating, Error> {
+    let mut survivors = report.all_rows();
+    let mut trace = Vec::new();
+    for position in 0..report.width() {
+        let remaining = survivors.len();
+        if remaining <= 1 {
+            break;
+        }
+        let column = Column { position, report, survivors: &survivors };
+        let ones = column.ones();
+        let (kept, tie) = match criterion.strategy.select(&column) {
+            Some(kept) => (kept, false),
+            None => (criterion.break_tie(position)?, true)
+        };
+        trace.push(FilterStep { position, remaining, ones, kept, tie });
+        survivors.retain(report.column(position), kept);
+    }
+    let outcome = match (survivors.first(), survivors.len()) {
+        (None, _) => Outcome::Empty,
+        (Some(row), 1) => Outcome::Unique(report.rows()[row]),
+        (Some(row), count) => Outcome::Duplicates { value: report.rows()[row], count }
+    };
+    Ok(Rating { outcome, trace })
+}
+
+// Duplicates are identical rows, so they still give a rating.
+pub fn life_support_rating(report: &Report, settings: &Settings) -> Result<u128, Error> {
+    let value = |criterion: &Criterion| rating(report, criterion)?.value()
+        .ok_or(Error::NoMatch { day: 3, quantity: criterion.name });
+    Ok(u128::from(value(&settings.oxygen)?) * u128::from(value(&settings.co2)?))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ColumnStats {
+    pub position: usize,
+    pub ones: usize,
+    pub zeros: usize
+}
+
+impl ColumnStats {
+    // Share of rows with a 1, or 0 for an empty report.
+    pub fn ratio(&self) -> f64 {
+        match self.ones + self.zeros {
+            0 => 0.0,
+            total => self.ones as f64 / total as f64
+        }
+    }
+
+    pub fn tie(&self) -> bool {
+        self.ones == self.zeros
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Statistics {
+    pub width: usize,
+    pub rows: usize,
+    pub columns: Vec<ColumnStats>,
+    pub gamma: u64,
+    pub epsilon: u64,
+    pub oxygen: Outcome,
+    pub co2: Outcome
+}
+
+pub fn statistics(report: &Report, settings: &Settings) -> Result<Statistics, Error> {
+    let all = report.all_rows();
+    let columns = (0..report.width())
+        .map(|position| {
+            let ones = report.ones(position, &all);
+            ColumnStats { position, ones, zeros: all.len() - ones }
+        })
+        .collect();
+    let gamma = gamma(report);
+    Ok(Statistics {
+        width: report.width(),
+        rows: all.len(),
+        columns,
+        gamma,
+        epsilon: epsilon(report, gamma),
+        oxygen: rating(report, &settings.oxygen)?.outcome,
+        co2: rating(report, &settings.co2)?.outcome
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::day_3::{bit_to_keep, co2_scrubber_rating, epsilon, gamma, life_support_rating, mean, oxygen_generator_rating, OxygenIterator, power_consumption, transpose};
+    use crate::day_3::{Criterion, epsilon, FilterStep, FixedMask, gamma, LeastCommon, life_support_rating, MostCommon, Outcome, power_consumption, rating, read_input,
+                       read_report, Report, RowSet, Settings, statistics, TiePolicy, Weighted};
+    use crate::error::{Error, ParseErrorKind};
+    use crate::solution::{find_day, Part};
+    use crate::parse::{Mode, ParseContext};
 
     #[test]
     fn test_gamma_rate() {
-        let example_input = include_str!("day3_example.txt");
-        assert_eq!(vec![1,0,1,1,0], gamma(example_input));
+        let example_input = include_str!("../examples/day3_example.txt");
+        assert_eq!(0b10110, gamma(&read_input(example_input, &mut ParseContext::default()).unwrap()));
     }
 
     #[test]
     fn test_epsilon_rate() {
-        assert_eq!(vec![0,1,1,0,0], epsilon(&vec![1,0,0,1,1]));
+        let report = Report::new(5, vec![0b10011]).unwrap();
+        assert_eq!(0b01100, epsilon(&report, 0b10011));
     }
     #[test]
     fn test_example() {
-        let example_input = include_str!("day3_example.txt");
-        assert_eq!(198, power_consumption(example_input));
+        let example_input = include_str!("../examples/day3_example.txt");
+        assert_eq!(198, power_consumption(&read_input(example_input, &mut ParseContext::default()).unwrap()));
+    }
+
+    #[test]
+    fn test_columns() {
+        let report = Report::new(3, vec![0b110, 0b011, 0b010]).unwrap();
+        let all = report.all_rows();
+        assert_eq!(vec![1, 3, 1], (0..3).map(|position| report.ones(position, &all)).collect::<Vec<usize>>());
+        assert_eq!(vec![0], report.column(0).iter().collect::<Vec<usize>>());
+        assert_eq!(vec![1], report.column(2).iter().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_row_set() {
+        let mut set = RowSet::full(130);
+        assert_eq!(130, set.len());
+        assert!(set.contains(129) && !set.contains(130));
+        let mut column = RowSet::empty(130);
+        column.insert(3);
+        column.insert(128);
+        set.retain(&column, true);
+        assert_eq!(vec![3, 128], set.iter().collect::<Vec<usize>>());
+        assert_eq!(Some(3), set.first());
+        set.retain(&column, false);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_read_input_errors() {
+        let err = read_input("00100\n11210\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day3 line 2: invalid binary digit \"2\" at column 3", err.to_string());
+
+        let too_wide = "1".repeat(65);
+        assert_eq!(ParseErrorKind::TooWide { max: 64 }, read_input(&too_wide, &mut ParseContext::default()).unwrap_err().kind);
+    }
+
+    #[test]
+    fn test_read_input_lenient() {
+        let mut context = ParseContext::new(Mode::Lenient);
+        assert_eq!(&[0b01, 0b11], read_input("01\n1 0\n11\n", &mut context).unwrap().rows());
+        assert_eq!(ParseErrorKind::InvalidDigit, context.warnings()[0].kind);
+    }
+
+    #[test]
+    fn test_ragged_lines() {
+        let err = read_input("00100\n11110\n0110\n10111\n101\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day3 line 3: \"0110\" has 4 digits, expected 5", err.to_string());
+
+        let mut context = ParseContext::new(Mode::Lenient);
+        let report = read_input("0x1\n011\n11\n110\n", &mut context).unwrap();
+        assert_eq!((3, vec![0b011, 0b110]), (report.width(), report.rows().to_vec()));
+        assert_eq!(vec![1, 3], context.warnings().iter().map(|warning| warning.line).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_declared_width() {
+        let report = read_report("100\n1\n11111\n", Some(5), &mut ParseContext::default()).unwrap();
+        assert_eq!((5, vec![0b00100, 0b00001, 0b11111]), (report.width(), report.rows().to_vec()));
+        assert_eq!(vec![2], report.column(0).iter().collect::<Vec<usize>>());
+
+        let err = read_report("100\n100000\n", Some(5), &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day3 line 2: \"100000\" is wider than 5 characters", err.to_string());
+        let err = read_report(&format!("100\n{}\n", "1".repeat(70)), Some(5), &mut ParseContext::default()).unwrap_err();
+        assert_eq!((2, 6, ParseErrorKind::TooWide { max: 5 }), (err.line, err.column, err.kind));
     }
 
     #[test]
-    fn test_transpose() {
-        let input = vec![vec![1,2,3], vec![4,5,6]];
-        let expected_output = vec![vec![1,4], vec![2,5], vec![3,6]];
-        assert_eq!(expected_output, transpose(&input));
+    fn test_blank_lines() {
+        let err = read_report("100\n\n11111\n", Some(5), &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day3 line 2: empty row", err.to_string());
+        let err = read_input("\n10110\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!((1, ParseErrorKind::EmptyRow), (err.line, err.kind));
+
+        let input = format!("{}\n\n", include_str!("../examples/day3_example.txt"));
+        let mut context = ParseContext::new(Mode::Lenient);
+        let report = read_report(&input, Some(5), &mut context).unwrap();
+        assert_eq!(12, report.rows().len());
+        assert_eq!(vec![13, 14], context.warnings().iter().map(|warning| warning.line).collect::<Vec<usize>>());
+
+        let mut context = ParseContext::default();
+        context.set_option("width", "5");
+        let err = find_day(3).unwrap().solve(&format!("{}\n\n\n", include_str!("../examples/day3_example.txt")), &Part::ALL, &mut context).err().unwrap();
+        assert_eq!("day3 line 13: empty row", err.to_string());
+
+        let mut context = ParseContext::default();
+        context.set_option("width", "12");
+        let answers = find_day(3).unwrap().solve("100\n1\n110\n", &[Part::One], &mut context).unwrap();
+        assert_eq!((0b100 * 0b1111_1111_1011).to_string(), answers[0].value);
+        context.set_option("width", "65");
+        let err = find_day(3).unwrap().solve("1\n", &Part::ALL, &mut context).err().unwrap();
+        assert_eq!("invalid value \"65\" for option width: expected a whole number from 1 to 64", err.to_string());
     }
 
     #[test]
-    fn test_mean() {
-        assert_eq!(1, mean(&vec![1,1,1]));
-        assert_eq!(3, mean(&vec![1,3,5]));
-        assert_eq!(1, mean(&vec![1,1,0,0]));
-        assert_eq!(1, mean(&vec![1,1,1,0]));
+    fn test_report_new() {
+        assert_eq!(Some(&[0b101, 0b010][..]), Report::new(3, vec![0b101, 0b010]).as_ref().map(Report::rows));
+        assert_eq!(None, Report::new(3, vec![0b101, 0b1000]));
+        assert_eq!(None, Report::new(0, vec![1]));
+        assert_eq!(None, Report::new(65, Vec::new()));
+        assert!(Report::new(64, vec![u64::MAX]).is_some());
     }
 
     #[test]
-    fn part1_result() {
-        let input = include_str!("day3.txt");
-        println!("Result: {}", power_consumption(input));
+    fn test_wide_report() {
+        let input = format!("{}\n0{}\n1{}\n", "1".repeat(64), "1".repeat(63), "0".repeat(63));
+        let report = read_input(&input, &mut ParseContext::default()).unwrap();
+        assert_eq!(64, report.width());
+        assert_eq!(Some(u64::MAX), rating(&report, &Criterion::OXYGEN).unwrap().value());
+        assert_eq!(Some(u64::MAX >> 1), rating(&report, &Criterion::CO2).unwrap().value());
+        assert_eq!(Ok(u128::from(u64::MAX) * u128::from(u64::MAX >> 1)), life_support_rating(&report, &Settings::default()));
+        assert_eq!(0, power_consumption(&report));
     }
-    
+
     #[test]
     fn test_oxygen_generator_rating() {
-        let input = include_str!("day3_example.txt");
-        assert_eq!(23, oxygen_generator_rating(input));
+        let input = include_str!("../examples/day3_example.txt");
+        let rating = rating(&read_input(input, &mut ParseContext::default()).unwrap(), &Criterion::OXYGEN).unwrap();
+        assert_eq!((Outcome::Unique(23), vec![4]), (rating.outcome, rating.ties()));
     }
-    
+
     #[test]
     fn test_co2_scrubber_rating() {
-        let input = include_str!("day3_example.txt");
-        assert_eq!(10, co2_scrubber_rating(input));
+        let input = include_str!("../examples/day3_example.txt");
+        let rating = rating(&read_input(input, &mut ParseContext::default()).unwrap(), &Criterion::CO2).unwrap();
+        assert_eq!((Outcome::Unique(10), vec![2]), (rating.outcome, rating.ties()));
+    }
+
+    #[test]
+    fn test_tie_policies() {
+        let report = Report::new(2, vec![0b10, 0b00]).unwrap();
+        let oxygen = rating(&report, &Criterion::OXYGEN).unwrap();
+        assert_eq!((Outcome::Unique(0b10), vec![0]), (oxygen.outcome, oxygen.ties()));
+        assert_eq!(Some(0b00), rating(&report, &Criterion::CO2).unwrap().value());
+
+        let prefer_zero = Criterion { tie: TiePolicy::PreferZero, ..Criterion::OXYGEN };
+        assert_eq!(Some(0b00), rating(&report, &prefer_zero).unwrap().value());
+        let strict = Criterion { tie: TiePolicy::Error, ..Criterion::CO2 };
+        let err = rating(&report, &strict).unwrap_err();
+        assert_eq!(Error::Tie { day: 3, position: 0, quantity: "CO2 scrubber rating" }, err);
+        assert_eq!("day3 position 0: CO2 scrubber rating is tied", err.to_string());
     }
 
     #[test]
-    fn test_iterator() {
-        let vec = vec![vec![1, 0], vec![0, 0]];
-        let mut iterator = OxygenIterator::new(vec);
-        iterator.next();
-        assert_eq!(vec![vec![1,0]], iterator.matrix);
-        assert_eq!(Option::None, iterator.next());
+    fn test_outcomes() {
+        let duplicates = Report::new(3, vec![0b101, 0b011, 0b101, 0b101]).unwrap();
+        let oxygen = rating(&duplicates, &Criterion::OXYGEN).unwrap();
+        assert_eq!(Outcome::Duplicates { value: 0b101, count: 3 }, oxygen.outcome);
+        assert_eq!(3, oxygen.trace.len());
+
+        let uniform = Report::new(2, vec![0b11, 0b11]).unwrap();
+        let co2 = rating(&uniform, &Criterion::CO2).unwrap();
+        assert_eq!(Outcome::Empty, co2.outcome);
+        assert_eq!(vec![FilterStep { position: 0, remaining: 2, ones: 2, kept: false, tie: false }], co2.trace);
+        let err = life_support_rating(&uniform, &Settings::default()).unwrap_err();
+        assert_eq!("day3: no rows match the CO2 scrubber rating", err.to_string());
+
+        assert_eq!(Outcome::Empty, rating(&Report::new(5, Vec::new()).unwrap(), &Criterion::OXYGEN).unwrap().outcome);
     }
 
     #[test]
-    fn test_iterator_consume() {
-        let vec = vec![vec![1, 0], vec![0, 0]];
-        let iterator = OxygenIterator::new(vec);
-        assert_eq!(vec![vec![1, 0]], iterator.last().unwrap())
+    fn test_trace() {
+        let input = include_str!("../examples/day3_example.txt");
+        let oxygen = rating(&read_input(input, &mut ParseContext::default()).unwrap(), &Criterion::OXYGEN).unwrap();
+        let survivors = oxygen.trace.iter().map(FilterStep::survivors).collect::<Vec<usize>>();
+        assert_eq!(vec![7, 4, 3, 2, 1], survivors);
+        assert_eq!("position 4: 1 of 2 rows have a 1, kept 1 on a tie, 1 left", oxygen.trace[4].to_string());
+        assert_eq!("position 1: 3 of 7 rows have a 1, kept 0, 4 left", oxygen.trace[1].to_string());
     }
 
     #[test]
-    fn test_to_keep() {
-        let vec = vec![vec![1, 0], vec![0, 0]];
-        assert_eq!(1, bit_to_keep(&vec, 0));
-        assert_eq!(0, bit_to_keep(&vec, 1));
+    fn test_strategies() {
+        let report = read_input(include_str!("../examples/day3_example.txt"), &mut ParseContext::default()).unwrap();
+        let rate = |strategy, tie| rating(&report, &Criterion { name: "test", strategy, tie }).unwrap().outcome;
+        assert_eq!(Outcome::Unique(23), rate(&MostCommon, TiePolicy::PreferOne));
+        assert_eq!(Outcome::Unique(10), rate(&LeastCommon, TiePolicy::PreferZero));
+        assert_eq!(Outcome::Unique(0b01010), rate(&FixedMask(0b01011), TiePolicy::Error));
+        assert_eq!(Outcome::Unique(0b11110), rate(&FixedMask(0b11111), TiePolicy::Error));
+        let uniform = Report::new(2, vec![0b11, 0b11]).unwrap();
+        let criterion = Criterion { name: "mask", strategy: &FixedMask(0b01), tie: TiePolicy::Error };
+        assert_eq!(Outcome::Empty, rating(&uniform, &criterion).unwrap().outcome);
+
+        // Row 00100 outweighs every other row put together, so each position follows it.
+        let mut weights = vec![1; 12];
+        weights[0] = 20;
+        weights[1] = 5;
+        assert_eq!(Outcome::Unique(0b00100), rate(&Weighted(weights), TiePolicy::Error));
+        let err = rating(&report, &Criterion { name: "even", strategy: &Weighted(Vec::new()), tie: TiePolicy::Error }).unwrap_err();
+        assert_eq!(Error::Tie { day: 3, position: 0, quantity: "even" }, err);
     }
 
     #[test]
-    fn test_life_support_example() {
-        let input = include_str!("day3_example.txt");
-        assert_eq!(230, life_support_rating(input));
+    fn test_statistics() {
+        let report = read_input(include_str!("../examples/day3_example.txt"), &mut ParseContext::default()).unwrap();
+        let stats = statistics(&report, &Settings::default()).unwrap();
+        assert_eq!((5, 12), (stats.width, stats.rows));
+        assert_eq!(vec![7, 5, 8, 7, 5], stats.columns.iter().map(|column| column.ones).collect::<Vec<usize>>());
+        assert!(stats.columns.iter().all(|column| !column.tie()));
+        assert_eq!((22, 9), (stats.gamma, stats.epsilon));
+        assert_eq!((Outcome::Unique(23), Outcome::Unique(10)), (stats.oxygen, stats.co2));
+
+        let tied = statistics(&Report::new(1, vec![0, 1]).unwrap(), &Settings::default()).unwrap();
+        assert!(tied.columns[0].tie());
+        assert_eq!(0.5, tied.columns[0].ratio());
+        assert_eq!(0.0, statistics(&Report::new(1, Vec::new()).unwrap(), &Settings::default()).unwrap().columns[0].ratio());
     }
 
     #[test]
-    fn part2_result() {
-        let input = include_str!("day3.txt");
-        println!("Part 2 result: {}", life_support_rating(input));
+    fn test_tie_options() {
+        let input = include_str!("../examples/day3_example.txt");
+        let mut context = ParseContext::default();
+        context.set_option("oxygen-tie", "zero");
+        assert_eq!("220", find_day(3).unwrap().solve(input, &[Part::Two], &mut context).unwrap()[0].value);
+        context.set_option("co2-tie", "error");
+        let err = find_day(3).unwrap().solve(input, &[Part::Two], &mut context).err().unwrap();
+        assert_eq!("day3 position 2: CO2 scrubber rating is tied", err.to_string());
+        context.set_option("co2-tie", "maybe");
+        let err = find_day(3).unwrap().solve(input, &[Part::Two], &mut context).err().unwrap();
+        assert_eq!("invalid value \"maybe\" for option co2-tie: expected one, zero or error", err.to_string());
+    }
+
+    #[test]
+    fn test_life_support_example() {
+        let input = include_str!("../examples/day3_example.txt");
+        assert_eq!(Ok(230), life_support_rating(&read_input(input, &mut ParseContext::default()).unwrap(), &Settings::default()));
     }
 }
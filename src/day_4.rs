@@ -1,251 +1,634 @@
-use std::slice::Iter;
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::error::{Error, ParseError, ParseErrorKind};
+use crate::parse::{number_error, ParseContext, split_columns};
+use crate::solution::Solution;
+
+pub struct Day4;
+
+pub struct Bingo {
+    pub boards: Vec<Board>,
+    pub drawn_numbers: DrawnNumbers,
+    pub rules: Vec<WinRule>,
+    pub wins: Vec<Win>
+}
+
+impl Solution for Day4 {
+    type Input = Bingo;
+    type Part1 = u64;
+    type Part2 = u64;
+
+    const OPTIONS: &'static [&'static str] = &["win"];
+
+    // Plays the game once here so both parts read their score from the same list of wins.
+    fn parse(input: &str, context: &mut ParseContext) -> Result<Self::Input, Error> {
+        let rules = match context.option("win") {
+            Some(value) => parse_rules(value).map_err(|reason| Error::invalid_option("win", value, &reason))?,
+            None => WinRule::DEFAULT.to_vec()
+        };
+        let (boards, drawn_numbers) = parse_input(input, context)?;
+        let wins = play(&boards, &drawn_numbers, &rules);
+        Ok(Bingo { boards, drawn_numbers, rules, wins })
+    }
 
-pub fn day4_part1(day4_input: &str) -> u32 {
-    let (boards, drawn_numbers) = parse_input(day4_input);
-    let maybe_bingo = run_bingo(&boards, &drawn_numbers);
-    maybe_bingo.map_or(0, calculate_result)
+    fn part1(bingo: &Self::Input) -> Result<Self::Part1, Error> {
+        Ok(scores(&bingo.wins).0)
+    }
+
+    fn part2(bingo: &Self::Input) -> Result<Self::Part2, Error> {
+        Ok(scores(&bingo.wins).1)
+    }
 }
 
-fn calculate_result((board, drawn_numbers): BingoResult) -> u32 {
-    let remaining_numbers = unmarked_numbers(board, &drawn_numbers);
-    let last_number = drawn_numbers.last().unwrap_or(&0);
-    remaining_numbers.iter().map(|num| u32::try_from(**num).unwrap_or(0)).sum::<u32>() * u32::try_from(*last_number).unwrap()
+// The scores of the first and last boards to win, or 0 when no board wins.
+fn scores(wins: &[Win]) -> (u64, u64) {
+    (wins.first().map_or(0, |win| win.score), wins.last().map_or(0, |win| win.score))
 }
 
-type Board = [[u8; 5]; 5];
+pub fn day4_scores(boards: &[Board], drawn_numbers: &[Number]) -> (u64, u64) {
+    scores(&play(boards, drawn_numbers, &WinRule::DEFAULT))
+}
 
-type DrawnNumbers = Vec<u8>;
+pub type Number = u32;
 
-type Input = (Vec<Board>, DrawnNumbers);
+#[derive(Clone, PartialEq, Debug)]
+pub struct Board {
+    width: usize,
+    cells: Vec<Number>
+}
+
+impl Board {
+    pub fn from_rows(rows: Vec<Vec<Number>>) -> Option<Board> {
+        let width = rows.first()?.len();
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+        Some(Board { width, cells: rows.concat() })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
 
-fn parse_board(input: &str) -> Board {
-    let mut output: Board = [[0; 5]; 5];
-    input.lines()
-        .filter(|line| !line.is_empty())
-        .map(|row| row.split(' ').filter_map(|n| n.parse::<u8>().ok()))
+    pub fn height(&self) -> usize {
+        self.cells.len() / self.width
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Number]> {
+        self.cells.chunks(self.width)
+    }
+
+    pub fn column(&self, col_index: usize) -> impl Iterator<Item = &Number> {
+        self.cells.iter().skip(col_index).step_by(self.width)
+    }
+
+    pub fn cells(&self) -> &[Number] {
+        &self.cells
+    }
+}
+
+pub type DrawnNumbers = Vec<Number>;
+
+pub type Input = (Vec<Board>, DrawnNumbers);
+
+fn parse_board(input: &str, first_line: usize, context: &mut ParseContext) -> Result<Board, ParseError> {
+    let mut rows: Vec<Vec<Number>> = Vec::new();
+    let lines = input.lines()
         .enumerate()
-        .for_each(|(index, row)| row.enumerate().for_each(|(col_index, cell)| output[index][col_index] = cell));
-    output
+        .filter(|(_, line)| !line.trim().is_empty());
+    for (line_offset, row) in lines {
+        let line = first_line + line_offset;
+        let cells = split_columns(row, ' ')
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(column, cell)| context.recover(cell.parse::<Number>()
+                .map_err(|err| ParseError::new(4, line, column, cell, number_error(&err)))))
+            .filter_map(Result::transpose)
+            .collect::<Result<Vec<Number>, ParseError>>()?;
+        let expected = rows.first().map_or(cells.len(), Vec::len);
+        if cells.len() != expected {
+            let kind = ParseErrorKind::RaggedBoard { expected, found: cells.len() };
+            return Err(ParseError::new(4, line, 1, row, kind));
+        }
+        rows.push(cells);
+    }
+    Board::from_rows(rows).ok_or_else(|| ParseError::new(4, first_line, 1, input, ParseErrorKind::EmptyBoard))
 }
 
-fn parse_boards(input: &str) -> Vec<Board> {
-    input.split("\n\n")
-        .filter(|block| !block.is_empty())
-        .map(|block| parse_board(block))
-        .collect()
+fn parse_boards(input: &str, first_line: usize, context: &mut ParseContext) -> Result<Vec<Board>, ParseError> {
+    let mut line = first_line;
+    let mut boards = Vec::new();
+    let blocks = input.split("\n\n")
+        .map(|block| {
+            let block_line = line;
+            line += block.matches('\n').count() + 2;
+            (block_line, block)
+        })
+        .filter(|(_, block)| !block.trim().is_empty());
+    for (block_line, block) in blocks {
+        let board = parse_board(block, block_line, context);
+        if let Some(board) = context.recover(board)? {
+            boards.push(board);
+        }
+    }
+    Ok(boards)
 }
 
-fn parse_header(input: &str) -> DrawnNumbers {
-    input.split(',').filter_map(|n| n.parse::<u8>().ok()).collect()
+fn parse_header(input: &str, context: &mut ParseContext) -> Result<DrawnNumbers, ParseError> {
+    split_columns(input, ',')
+        .map(|(column, n)| context.recover(n.parse::<Number>()
+            .map_err(|err| ParseError::new(4, 1, column, n, number_error(&err)))))
+        .filter_map(Result::transpose)
+        .collect()
 }
 
-fn parse_input(input: &str) -> Input {
+pub fn parse_input(input: &str, context: &mut ParseContext) -> Result<Input, ParseError> {
     let mut lines = input.lines();
-    let header_string = lines.next().unwrap();
-    let drawn_numbers = parse_header(header_string);
+    let header_string = lines.next()
+        .ok_or_else(|| ParseError::new(4, 1, 1, "", ParseErrorKind::MissingHeader))?;
+    let drawn_numbers = parse_header(header_string, context)?;
 
     let boards_input = lines.collect::<Vec<&str>>().join("\n");
 
-    (parse_boards(&boards_input), drawn_numbers)
+    Ok((parse_boards(&boards_input, 2, context)?, drawn_numbers))
 }
 
-fn has_bingo(board: &Board, drawn_numbers: &[u8]) -> bool {
-    has_row_bingo(board, drawn_numbers) || has_column_bingo(board, drawn_numbers)
+#[derive(Clone, PartialEq, Debug)]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    positions: Vec<usize>
 }
 
-fn has_column_bingo(board: &Board, drawn_numbers: &[u8]) -> bool {
-    let bingo_column = (0..5)
-        .map(|col_index: usize| board.iter()
-            .filter_map(|row| row.get(col_index).copied()).collect::<Vec<u8>>()
-        ).find(|column| contains_all(&column[..], drawn_numbers));
-    bingo_column.is_some()
-}
+impl FromStr for Mask {
+    type Err = String;
 
-fn has_row_bingo(board: &Board, drawn_numbers: &[u8]) -> bool {
-    let bingo_row = board.iter().find(|row| contains_all(&row[..], drawn_numbers));
-    bingo_row.is_some()
+    // Rows separated by '/', with '1' for a cell that must be marked and '0' for one that need not be.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s.split('/').collect::<Vec<&str>>();
+        let width = rows[0].len();
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(String::from("mask rows must all have the same length"));
+        }
+        let positions = rows.concat().chars()
+            .enumerate()
+            .filter_map(|(position, char)| match char {
+                '1' => Some(Ok(position)),
+                '0' => None,
+                _ => Some(Err(format!("unexpected '{}' in mask", char)))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+        if positions.is_empty() {
+            return Err(String::from("mask has no marked cells"));
+        }
+        Ok(Mask { width, height: rows.len(), positions })
+    }
 }
 
-fn contains_all(one: &[u8], other: &[u8]) -> bool {
-    one.iter().all(|n| other.contains(n))
+#[derive(Clone, PartialEq, Debug)]
+pub enum WinRule {
+    Rows,
+    Columns,
+    Diagonals,
+    Corners,
+    Blackout,
+    // Only applies to boards with the same dimensions as the mask.
+    Mask(Mask)
 }
 
-struct SublistIterator<'a> {
-    output: Vec<u8>,
-    iter: Iter<'a, u8>
+impl WinRule {
+    pub const DEFAULT: [WinRule; 2] = [WinRule::Rows, WinRule::Columns];
+
+    fn patterns(&self, width: usize, height: usize) -> Vec<Vec<usize>> {
+        match self {
+            WinRule::Rows => (0..height).map(|row| (0..width).map(|col| row * width + col).collect()).collect(),
+            WinRule::Columns => (0..width).map(|col| (0..height).map(|row| row * width + col).collect()).collect(),
+            WinRule::Diagonals if width == height => vec![
+                (0..width).map(|i| i * width + i).collect(),
+                (0..width).map(|i| i * width + width - 1 - i).collect()
+            ],
+            WinRule::Diagonals => Vec::new(),
+            WinRule::Corners => {
+                let mut corners = vec![0, width - 1, (height - 1) * width, height * width - 1];
+                corners.sort_unstable();
+                corners.dedup();
+                vec![corners]
+            }
+            WinRule::Blackout => vec![(0..width * height).collect()],
+            WinRule::Mask(mask) if mask.width == width && mask.height == height => vec![mask.positions.clone()],
+            WinRule::Mask(_) => Vec::new()
+        }
+    }
 }
 
-impl SublistIterator<'_> {
-    fn new(origin: &[u8]) -> SublistIterator {
-        SublistIterator {
-            output: Vec::new(),
-            iter: origin.iter()
+impl FromStr for WinRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rows" => Ok(WinRule::Rows),
+            "columns" => Ok(WinRule::Columns),
+            "diagonals" => Ok(WinRule::Diagonals),
+            "corners" => Ok(WinRule::Corners),
+            "blackout" => Ok(WinRule::Blackout),
+            _ => match s.strip_prefix("mask:") {
+                Some(mask) => Ok(WinRule::Mask(Mask::from_str(mask)?)),
+                None => Err(format!("unknown win rule \"{}\"", s))
+            }
         }
     }
+}
 
-    fn drawn_numbers(self) -> Vec<u8> {
-        self.output
+pub fn parse_rules(input: &str) -> Result<Vec<WinRule>, String> {
+    input.split(',').map(WinRule::from_str).collect()
+}
+
+// The winning patterns for one board size, and for each cell the patterns it belongs to.
+struct Layout {
+    sizes: Vec<usize>,
+    cell_patterns: Vec<Vec<usize>>
+}
+
+impl Layout {
+    fn new(width: usize, height: usize, rules: &[WinRule]) -> Layout {
+        let patterns = rules.iter().flat_map(|rule| rule.patterns(width, height)).collect::<Vec<Vec<usize>>>();
+        let mut cell_patterns = vec![Vec::new(); width * height];
+        for (pattern, positions) in patterns.iter().enumerate() {
+            positions.iter().for_each(|position| cell_patterns[*position].push(pattern));
+        }
+        Layout { sizes: patterns.iter().map(Vec::len).collect(), cell_patterns }
     }
 }
 
-impl Iterator for SublistIterator<'_> {
-    type Item = Vec<u8>;
+struct BoardState {
+    marked: Vec<bool>,
+    pattern_hits: Vec<usize>,
+    unmarked_sum: u64,
+    has_won: bool
+}
+
+impl BoardState {
+    fn new(board: &Board, layout: &Layout) -> BoardState {
+        BoardState {
+            marked: vec![false; board.cells().len()],
+            pattern_hits: vec![0; layout.sizes.len()],
+            unmarked_sum: board.cells().iter().map(|num| u64::from(*num)).sum(),
+            has_won: false
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(next) = self.iter.next() {
-            self.output.push(*next);
-            Some(self.output.clone())
-        } else {
-            None
+    fn mark(&mut self, board: &Board, layout: &Layout, position: usize) -> bool {
+        if self.marked[position] {
+            return false;
+        }
+        self.marked[position] = true;
+        self.unmarked_sum -= u64::from(board.cells()[position]);
+        let mut completed = false;
+        for pattern in &layout.cell_patterns[position] {
+            self.pattern_hits[*pattern] += 1;
+            completed |= self.pattern_hits[*pattern] == layout.sizes[*pattern];
         }
+        completed
     }
 }
 
-type BingoResult<'a> = (&'a Board, Vec<u8>);
+pub struct Game<'a> {
+    boards: &'a [Board],
+    layouts: HashMap<(usize, usize), Layout>,
+    states: Vec<BoardState>,
+    positions: HashMap<Number, Vec<(usize, usize)>>
+}
 
-fn run_bingo<'a>(boards: &'a [Board], drawn_numbers: &'_[u8]) -> Option<BingoResult<'a>> {
-    let mut iter = SublistIterator::new(drawn_numbers);
-    iter
-        .find_map(|drawn_numbers| boards.iter()
-            .find(|board| has_bingo(board, &drawn_numbers[..]))
-        ).map(|bingo_board| (bingo_board, iter.drawn_numbers()))
+impl Game<'_> {
+    pub fn new<'a>(boards: &'a [Board], rules: &[WinRule]) -> Game<'a> {
+        let mut layouts = HashMap::new();
+        let mut positions: HashMap<Number, Vec<(usize, usize)>> = HashMap::new();
+        for (index, board) in boards.iter().enumerate() {
+            let size = (board.width(), board.height());
+            layouts.entry(size).or_insert_with(|| Layout::new(size.0, size.1, rules));
+            for (position, number) in board.cells().iter().enumerate() {
+                positions.entry(*number).or_default().push((index, position));
+            }
+        }
+        let states = boards.iter()
+            .map(|board| BoardState::new(board, &layouts[&(board.width(), board.height())]))
+            .collect();
+        Game { boards, layouts, states, positions }
+    }
+
+    // Marks the number on every board and returns the boards that won with it, in board order.
+    // A board that repeats the number has every copy marked before its win is recorded.
+    pub fn draw(&mut self, number: Number) -> Vec<usize> {
+        let mut winners = Vec::new();
+        for (index, position) in self.positions.get(&number).map_or(&[][..], Vec::as_slice) {
+            let board = &self.boards[*index];
+            let layout = &self.layouts[&(board.width(), board.height())];
+            let state = &mut self.states[*index];
+            if !state.has_won && state.mark(board, layout, *position) && !winners.contains(index) {
+                winners.push(*index);
+            }
+        }
+        winners.iter().for_each(|index| self.states[*index].has_won = true);
+        winners.sort_unstable();
+        winners
+    }
+
+    pub fn unmarked_sum(&self, board: usize) -> u64 {
+        self.states[board].unmarked_sum
+    }
 }
 
-fn unmarked_numbers<'a>(board: &'a Board, drawn_numbers: &'_[u8]) -> Vec<&'a u8> {
-    board.iter()
-        .flatten()
-        .filter(|num| !drawn_numbers.contains(num))
-        .collect()
+#[derive(Clone, PartialEq, Debug)]
+pub struct Win {
+    pub board: usize,
+    pub draw_index: usize,
+    pub number: Number,
+    pub score: u64
+}
+
+pub fn play(boards: &[Board], drawn_numbers: &[Number], rules: &[WinRule]) -> Vec<Win> {
+    let mut game = Game::new(boards, rules);
+    let mut wins = Vec::new();
+    for (draw_index, number) in drawn_numbers.iter().enumerate() {
+        for board in game.draw(*number) {
+            let score = game.unmarked_sum(board) * u64::from(*number);
+            wins.push(Win { board, draw_index, number: *number, score });
+        }
+    }
+    wins
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::day4_part1;
-    use crate::day_4::{Board, DrawnNumbers, has_bingo, has_column_bingo, has_row_bingo, parse_board, parse_header, parse_input, run_bingo, SublistIterator, unmarked_numbers};
+    use crate::day_4::{Board, Day4, day4_scores, DrawnNumbers, Game, Number, parse_board, parse_header, parse_input, parse_rules, play, Win, WinRule};
+    use crate::error::ParseErrorKind;
+    use crate::parse::{Mode, ParseContext};
+    use crate::solution::Solution;
 
     #[test]
     fn test_parse_board() {
         let input = "1 2 3 4 5\n\n6 7 8 9 10\n\n11 12 13 14 15\n\n16 17 18 19 20\n\n21 22 23 24 25";
         let expected_board: Board = make_board();
-        assert_eq!(expected_board, parse_board(input));
+        assert_eq!(expected_board, parse_board(input, 1, &mut ParseContext::default()).unwrap());
     }
 
     fn make_board() -> Board {
-        [[1, 2, 3, 4, 5], [6, 7, 8, 9, 10], [11, 12, 13, 14, 15], [16, 17, 18, 19, 20], [21, 22, 23, 24, 25]]
+        Board::from_rows(vec![vec![1, 2, 3, 4, 5], vec![6, 7, 8, 9, 10], vec![11, 12, 13, 14, 15], vec![16, 17, 18, 19, 20], vec![21, 22, 23, 24, 25]]).unwrap()
     }
 
     #[test]
     fn test_parse_header() {
         let input = "1,2,3,4,5";
-        assert_eq!(vec![1,2,3,4,5], parse_header(input));
+        assert_eq!(vec![1,2,3,4,5], parse_header(input, &mut ParseContext::default()).unwrap());
     }
 
     #[test]
     fn test_parse_input() {
-        let input = include_str!("day4_example.txt");
+        let input = include_str!("../examples/day4_example.txt");
+
+        let expected_drawn_numbers: DrawnNumbers = vec![7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1];
+
+        let expected_board_1 = Board::from_rows(vec![
+            vec![22, 13, 17, 11, 0],
+            vec![8, 2, 23, 4, 24],
+            vec![21, 9, 14, 16, 7],
+            vec![6, 10, 3, 18, 5],
+            vec![1, 12, 20, 15, 19]
+        ]).unwrap();
+
+        let expected_board_2 = Board::from_rows(vec![
+            vec![3, 15, 0, 2, 22],
+            vec![9, 18, 13, 17, 5],
+            vec![19, 8, 7, 25, 23],
+            vec![20, 11, 10, 24, 4],
+            vec![14, 21, 16, 12, 6]
+        ]).unwrap();
+
+        let expected_board_3 = Board::from_rows(vec![
+            vec![14, 21, 17, 24, 4],
+            vec![10, 16, 15, 9, 19],
+            vec![18, 8, 23, 26, 20],
+            vec![22, 11, 13, 6, 5],
+            vec![2, 0, 12, 3, 7]
+        ]).unwrap();
+
+        assert_eq!((vec![expected_board_1, expected_board_2, expected_board_3], expected_drawn_numbers), parse_input(input, &mut ParseContext::default()).unwrap());
+    }
 
-        let expected_drawn_numbers: Vec<u8> = vec![7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1];
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(ParseErrorKind::MissingHeader, parse_input("", &mut ParseContext::default()).unwrap_err().kind);
 
-        let expected_board_1 = [
-            [22, 13, 17, 11, 0],
-            [8, 2, 23, 4, 24],
-            [21, 9, 14, 16, 7],
-            [6, 10, 3, 18, 5],
-            [1, 12, 20, 15, 19]
-        ];
+        let bad_header = parse_input("7,4,x,5\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day4 line 1: invalid number \"x\"", bad_header.to_string());
+        assert_eq!(5, bad_header.column);
 
-        let expected_board_2 = [
-            [3, 15, 0, 2, 22],
-            [9, 18, 13, 17, 5],
-            [19, 8, 7, 25, 23],
-            [20, 11, 10, 24, 4],
-            [14, 21, 16, 12, 6]
-        ];
+        let input = "7,4\n\n1 2 3 4 5\n\n6 7 8 9 10\n11 12 13 14 15 16\n";
+        let ragged = parse_input(input, &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day4 line 6: board row \"11 12 13 14 15 16\" has 6 numbers, expected 5", ragged.to_string());
+    }
 
-        let expected_board_3 = [
-            [14, 21, 17, 24, 4],
-            [10, 16, 15, 9, 19],
-            [18, 8, 23, 26, 20],
-            [22, 11, 13, 6, 5],
-            [2, 0, 12, 3, 7]
-        ];
+    #[test]
+    fn test_parse_number_limit() {
+        let (boards, drawn_numbers) = parse_input("4294967295\n\n4294967295 1\n", &mut ParseContext::default()).unwrap();
+        assert_eq!(vec![4294967295], drawn_numbers);
+        assert_eq!(&[4294967295, 1], boards[0].cells());
+
+        let header = parse_input("7,4294967296\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!("day4 line 1: number \"4294967296\" is out of range", header.to_string());
+        let cell = parse_input("7\n\n1 4294967296\n", &mut ParseContext::default()).unwrap_err();
+        assert_eq!((3, 3, ParseErrorKind::OutOfRange), (cell.line, cell.column, cell.kind));
+    }
+
+    #[test]
+    fn test_parse_large_board() {
+        let rows = (0..7).map(|row| (0..7).map(|col| (row * 7 + col) * 20 + 40).map(|n| n.to_string()).collect::<Vec<String>>().join(" ")).collect::<Vec<String>>();
+        let input = format!("1000,40\n\n{}\n", rows.join("\n"));
+        let (boards, drawn_numbers) = parse_input(&input, &mut ParseContext::default()).unwrap();
+        assert_eq!(vec![1000, 40], drawn_numbers);
+        assert_eq!((7, 7), (boards[0].width(), boards[0].height()));
+        assert_eq!(Some(&1000), boards[0].cells().last());
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let mut context = ParseContext::new(Mode::Lenient);
+        let input = "7,x,4\n\n1 2\n3 4\n\n5 6\n7 ?\n\n8 9\n10 11\n";
+        let (boards, drawn_numbers) = parse_input(input, &mut context).unwrap();
+        assert_eq!(vec![7, 4], drawn_numbers);
+        assert_eq!(vec![vec![1, 2], vec![8, 9]], boards.iter().map(|board| board.rows().next().unwrap().to_vec()).collect::<Vec<DrawnNumbers>>());
+        let warnings = context.warnings().iter().map(|warning| (warning.line, warning.kind.clone())).collect::<Vec<(usize, ParseErrorKind)>>();
+        let ragged = ParseErrorKind::RaggedBoard { expected: 2, found: 1 };
+        assert_eq!(vec![(1, ParseErrorKind::InvalidNumber), (7, ParseErrorKind::InvalidNumber), (7, ragged)], warnings);
+    }
 
-        assert_eq!((vec![expected_board_1, expected_board_2, expected_board_3], expected_drawn_numbers), parse_input(input));
+    fn draw_all(game: &mut Game, numbers: &[Number]) -> Vec<usize> {
+        numbers.iter().flat_map(|number| game.draw(*number)).collect()
     }
 
     #[test]
-    fn test_has_row_bingo() {
-        let board: Board = make_board();
+    fn test_row_win() {
+        let boards = vec![make_board()];
+        let mut game = Game::new(&boards, &WinRule::DEFAULT);
 
-        assert_eq!(false, has_row_bingo(&board, &vec![1,2]));
-        assert_eq!(true, has_row_bingo(&board, &vec![6,7,8,9, 10]));
+        assert!(draw_all(&mut game, &[1,2]).is_empty());
+        assert!(draw_all(&mut game, &[6,7,8,9]).is_empty());
+        assert_eq!(vec![0], game.draw(10));
     }
 
     #[test]
-    fn test_has_column_bingo() {
-        let board: Board = make_board();
+    fn test_column_win() {
+        let boards = vec![make_board()];
+        let mut game = Game::new(&boards, &WinRule::DEFAULT);
 
-        assert!(!has_column_bingo(&board, &vec![1,2]));
-        assert!(has_column_bingo(&board, &vec![1,6,11,16,21]));
+        assert!(draw_all(&mut game, &[1,6,11,16]).is_empty());
+        assert_eq!(vec![0], game.draw(21));
     }
 
     #[test]
-    fn test_has_bingo() {
-        let board: Board = make_board();
+    fn test_winning_board_only_wins_once() {
+        let boards = vec![make_board(), make_board()];
+        let mut game = Game::new(&boards, &WinRule::DEFAULT);
 
-        assert!(!has_bingo(&board, &vec![1,2,3]));
-        assert!(has_bingo(&board, &vec![6,7,8,9,10]));
-        assert!(has_bingo(&board, &vec![2,7,12,17,22]));
+        assert_eq!(vec![0, 1], draw_all(&mut game, &[2,7,12,17,22]));
+        assert!(draw_all(&mut game, &[1,6,11,16,21]).is_empty());
     }
 
     #[test]
-    fn test_sublist_iterator() {
-        let list: [u8; 5] = [1,2,3,4,5];
+    fn test_repeated_number_on_board() {
+        let boards = vec![Board::from_rows(vec![vec![5, 1], vec![2, 5]]).unwrap()];
+        assert_eq!(vec![Win { board: 0, draw_index: 1, number: 5, score: 2 * 5 }], play(&boards, &[1, 5], &WinRule::DEFAULT));
+
+        let mut game = Game::new(&boards, &[WinRule::Diagonals]);
+        assert_eq!(vec![0], game.draw(5));
+        assert_eq!(3, game.unmarked_sum(0));
+    }
 
-        let mut iter = SublistIterator::new(&list);
+    #[test]
+    fn test_repeated_draw_is_marked_once() {
+        let boards = vec![make_board()];
+        let mut game = Game::new(&boards, &WinRule::DEFAULT);
 
-        assert_eq!(vec![1], iter.next().unwrap());
-        assert_eq!(vec![1,2], iter.next().unwrap());
-        assert_eq!(vec![1,2,3], iter.next().unwrap());
-        assert_eq!(vec![1,2,3,4], iter.next().unwrap());
-        assert_eq!(vec![1,2,3,4,5], iter.next().unwrap());
-        assert_eq!(None, iter.next());
+        assert!(draw_all(&mut game, &[1,1,1,1,2,3,4]).is_empty());
+        assert_eq!(vec![0], game.draw(5));
     }
 
     #[test]
-    fn test_run_bingo() {
+    fn test_play() {
         let bingo_board = make_board();
         let no_bingo_board = make_no_bingo_board();
 
-        let boards = vec![bingo_board, no_bingo_board];
-        let should_be_empty = run_bingo(&boards, &vec![1]);
-        assert!(should_be_empty.is_none());
+        let boards = vec![no_bingo_board, bingo_board];
+        let should_be_empty = play(&boards, &[1], &WinRule::DEFAULT);
+        assert!(should_be_empty.is_empty());
 
-        let should_be_bingo = run_bingo(&boards, &vec![1,2,3,4,5,6]);
+        let wins = play(&boards, &[1,2,3,4,5,6], &WinRule::DEFAULT);
 
-        assert!(should_be_bingo.is_some());
-        let (board, drawn_numbers) = should_be_bingo.unwrap();
-        assert_eq!(bingo_board, *board);
-        assert_eq!(vec![1,2,3,4,5], drawn_numbers)
+        let expected_score = (6..=25).sum::<u64>() * 5;
+        assert_eq!(vec![Win { board: 1, draw_index: 4, number: 5, score: expected_score }], wins);
+    }
 
+    #[test]
+    fn test_play_orders_all_wins() {
+        let (boards, drawn_numbers) = parse_input(include_str!("../examples/day4_example.txt"), &mut ParseContext::default()).unwrap();
+        let wins = play(&boards, &drawn_numbers, &WinRule::DEFAULT);
+        let order = wins.iter().map(|win| (win.board, win.draw_index, win.number)).collect::<Vec<(usize, usize, Number)>>();
+        assert_eq!(vec![(2, 11, 24), (0, 13, 16), (1, 14, 13)], order);
+        assert_eq!(4512, wins[0].score);
+        assert_eq!(1924, wins[2].score);
     }
 
     #[test]
-    fn test_unmarked() {
-        let board = make_board();
+    fn test_unmarked_sum() {
+        let boards = vec![make_board()];
+        let mut game = Game::new(&boards, &WinRule::DEFAULT);
 
-        let marked_numbers: DrawnNumbers = (1..=23).collect();
-        let unmarked = unmarked_numbers(&board, &marked_numbers);
+        draw_all(&mut game, &[1,7,13,19,99]);
 
-        assert_eq!(vec![24,25], unmarked.iter().map(|num| *num.clone()).collect::<Vec<u8>>());
+        assert_eq!(325 - 40, game.unmarked_sum(0));
     }
 
     #[test]
     fn test_example() {
-        assert_eq!(4512, day4_part1(include_str!("day4_example.txt")));
+        let (boards, drawn_numbers) = parse_input(include_str!("../examples/day4_example.txt"), &mut ParseContext::default()).unwrap();
+        assert_eq!((4512, 1924), day4_scores(&boards, &drawn_numbers));
+        assert_eq!((0, 0), day4_scores(&boards, &[]));
     }
 
     fn make_no_bingo_board() -> Board {
-        [[99; 5]; 5]
+        Board::from_rows(vec![vec![99; 5]; 5]).unwrap()
+    }
+
+    #[test]
+    fn test_board_from_rows() {
+        assert!(Board::from_rows(vec![vec![1, 2], vec![3]]).is_none());
+        assert!(Board::from_rows(Vec::new()).is_none());
+        let board = Board::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        assert_eq!((3, 2), (board.width(), board.height()));
+        assert_eq!(vec![2, 5], board.column(1).copied().collect::<Vec<Number>>());
+    }
+
+    #[test]
+    fn test_diagonal_rule() {
+        let boards = vec![make_board()];
+        let rules = [WinRule::Diagonals];
+        let mut game = Game::new(&boards, &rules);
+
+        assert!(draw_all(&mut game, &[1,2,3,4,5,7,13,19]).is_empty());
+        assert_eq!(vec![0], game.draw(25));
+
+        let mut game = Game::new(&boards, &rules);
+        assert_eq!(vec![0], draw_all(&mut game, &[5,9,13,17,21]));
+    }
+
+    #[test]
+    fn test_diagonals_need_square_board() {
+        let boards = vec![Board::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap()];
+        let mut game = Game::new(&boards, &[WinRule::Diagonals]);
+        assert!(draw_all(&mut game, &[1,2,3,4,5,6]).is_empty());
+    }
+
+    #[test]
+    fn test_corners_and_blackout() {
+        let boards = vec![make_board()];
+        let mut game = Game::new(&boards, &[WinRule::Corners]);
+        assert_eq!(vec![0], draw_all(&mut game, &[1,5,21,25]));
+
+        let single_row = vec![Board::from_rows(vec![vec![1, 2, 3]]).unwrap()];
+        let mut game = Game::new(&single_row, &[WinRule::Corners]);
+        assert_eq!(vec![0], draw_all(&mut game, &[1,3]));
+
+        let mut game = Game::new(&boards, &[WinRule::Blackout]);
+        assert!(draw_all(&mut game, &(1..25).collect::<DrawnNumbers>()).is_empty());
+        assert_eq!(vec![0], game.draw(25));
+        assert_eq!(0, game.unmarked_sum(0));
+    }
+
+    #[test]
+    fn test_mask_rule() {
+        let rules = parse_rules("mask:010/111/010").unwrap();
+        let boards = vec![Board::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]).unwrap(), make_board()];
+        let mut game = Game::new(&boards, &rules);
+        assert_eq!(vec![0], draw_all(&mut game, &[2,4,5,6,8,1,3,7,9,10,11,12,13,14,15]));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!(vec![WinRule::Rows, WinRule::Diagonals, WinRule::Blackout], parse_rules("rows,diagonals,blackout").unwrap());
+        assert_eq!(Err(String::from("unknown win rule \"stars\"")), parse_rules("rows,stars"));
+        assert_eq!(Err(String::from("mask rows must all have the same length")), parse_rules("mask:11/1"));
+        assert_eq!(Err(String::from("mask has no marked cells")), parse_rules("mask:00/00"));
+    }
+
+    #[test]
+    fn test_solve_with_win_option() {
+        let mut context = ParseContext::default();
+        context.set_option("win", "diagonals");
+        let bingo = Day4::parse(include_str!("../examples/day4_example.txt"), &mut context).unwrap();
+        assert_eq!(vec![WinRule::Diagonals], bingo.rules);
+        assert_eq!(vec![2, 1, 0], bingo.wins.iter().map(|win| win.board).collect::<Vec<usize>>());
+
+        context.set_option("win", "rows,hexagons");
+        let err = Day4::parse(include_str!("../examples/day4_example.txt"), &mut context).err().unwrap();
+        assert_eq!("invalid value \"rows,hexagons\" for option win: unknown win rule \"hexagons\"", err.to_string());
     }
 }
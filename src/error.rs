@@ -0,0 +1,129 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+
+#[derive(PartialEq, Debug)]
+pub enum Error {
+    Parse(ParseError),
+    Io(String),
+    Input { day: u8, reason: String },
+    Overflow { day: u8, step: usize, quantity: &'static str },
+    Tie { day: u8, position: usize, quantity: &'static str },
+    NoMatch { day: u8, quantity: &'static str },
+    InvalidOption { name: String, value: String, reason: String }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(err) => err.fmt(f),
+            Error::Io(message) => write!(f, "could not read input: {}", message),
+            Error::Input { day, reason } => write!(f, "could not load day {} input: {}", day, reason),
+            Error::Overflow { day, step, quantity } => write!(f, "day{} step {}: {} overflowed", day, step, quantity),
+            Error::Tie { day, position, quantity } => write!(f, "day{} position {}: {} is tied", day, position, quantity),
+            Error::NoMatch { day, quantity } => write!(f, "day{}: no rows match the {}", day, quantity),
+            Error::InvalidOption { name, value, reason } =>
+                write!(f, "invalid value \"{}\" for option {}: {}", value, name, reason)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    pub fn invalid_option(name: &str, value: &str, reason: &str) -> Error {
+        Error::InvalidOption { name: String::from(name), value: String::from(value), reason: String::from(reason) }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err.to_string())
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParseErrorKind {
+    InvalidNumber,
+    OutOfRange,
+    InvalidDigit,
+    UnknownAction,
+    MissingValue,
+    UnexpectedToken,
+    MissingHeader,
+    EmptyBoard,
+    RaggedBoard { expected: usize, found: usize },
+    TooWide { max: usize },
+    WrongWidth { expected: usize, found: usize },
+    EmptyRow,
+    MissingBlock,
+    UnclosedBlock,
+    TooManySteps { max: usize }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParseError {
+    pub day: u8,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub kind: ParseErrorKind
+}
+
+impl ParseError {
+    pub fn new(day: u8, line: usize, column: usize, text: &str, kind: ParseErrorKind) -> ParseError {
+        ParseError { day, line, column, text: String::from(text), kind }
+    }
+
+    pub fn on_line(mut self, line: usize) -> ParseError {
+        self.line = line;
+        self
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "day{} line {}: ", self.day, self.line)?;
+        match &self.kind {
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number \"{}\"", self.text),
+            ParseErrorKind::OutOfRange => write!(f, "number \"{}\" is out of range", self.text),
+            ParseErrorKind::InvalidDigit => write!(f, "invalid binary digit \"{}\" at column {}", self.text, self.column),
+            ParseErrorKind::UnknownAction => write!(f, "unknown action \"{}\"", self.text),
+            ParseErrorKind::MissingValue => write!(f, "missing value after \"{}\"", self.text),
+            ParseErrorKind::UnexpectedToken => write!(f, "unexpected \"{}\" at column {}", self.text, self.column),
+            ParseErrorKind::MissingHeader => write!(f, "missing header"),
+            ParseErrorKind::EmptyBoard => write!(f, "empty board"),
+            ParseErrorKind::RaggedBoard { expected, found } =>
+                write!(f, "board row \"{}\" has {} numbers, expected {}", self.text, found, expected),
+            ParseErrorKind::TooWide { max } => write!(f, "\"{}\" is wider than {} characters", self.text, max),
+            ParseErrorKind::WrongWidth { expected, found } =>
+                write!(f, "\"{}\" has {} digits, expected {}", self.text, found, expected),
+            ParseErrorKind::EmptyRow => write!(f, "empty row"),
+            ParseErrorKind::MissingBlock => write!(f, "expected \"{{\" after \"{}\"", self.text),
+            ParseErrorKind::UnclosedBlock => write!(f, "\"{}\" block is never closed", self.text),
+            ParseErrorKind::TooManySteps { max } => write!(f, "course expands to more than {} steps", max)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, ParseError, ParseErrorKind};
+
+    #[test]
+    fn test_display() {
+        let err = ParseError::new(2, 1, 1, "backward", ParseErrorKind::UnknownAction).on_line(17);
+        assert_eq!("day2 line 17: unknown action \"backward\"", Error::from(err).to_string());
+    }
+
+    #[test]
+    fn test_display_with_column() {
+        let err = ParseError::new(3, 4, 3, "2", ParseErrorKind::InvalidDigit);
+        assert_eq!("day3 line 4: invalid binary digit \"2\" at column 3", err.to_string());
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::Write;
+use std::str::FromStr;
+use crate::day_2::{Instruction, model, Step, trajectory};
+use crate::error::Error;
+use crate::render::write_svg;
+use crate::solution::Part;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Csv,
+    Json,
+    Svg
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "svg" => Ok(Format::Svg),
+            _ => Err(format!("invalid format \"{}\", expected csv, json or svg", s))
+        }
+    }
+}
+
+// Csv and json follow the given part; svg always overlays both.
+pub fn write_course<W: Write>(out: &mut W, course: &[Instruction], part: Part, format: Format) -> Result<(), Error> {
+    match format {
+        Format::Csv => write_csv(out, &steps(course, part)?)?,
+        Format::Json => write_json(out, &steps(course, part)?)?,
+        Format::Svg => write_svg(out, &steps(course, Part::One)?, &steps(course, Part::Two)?)?
+    }
+    Ok(())
+}
+
+fn steps(course: &[Instruction], part: Part) -> Result<Vec<Step>, Error> {
+    trajectory(course, model(part)).collect()
+}
+
+fn write_csv<W: Write>(out: &mut W, steps: &[Step]) -> std::io::Result<()> {
+    writeln!(out, "step,instruction,forward,depth,aim")?;
+    for step in steps {
+        writeln!(out, "{},{},{},{},{}", step.step, step.instruction, step.state.forward, step.state.depth, step.state.aim)?;
+    }
+    Ok(())
+}
+
+// One object per line; instructions are plain words and digits, so no string escaping is needed.
+fn write_json<W: Write>(out: &mut W, steps: &[Step]) -> std::io::Result<()> {
+    write!(out, "[")?;
+    for (index, step) in steps.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        write!(out, "{}\n  {{\"step\": {}, \"instruction\": \"{}\", \"forward\": {}, \"depth\": {}, \"aim\": {}}}",
+               separator, step.step, step.instruction, step.state.forward, step.state.depth, step.state.aim)?;
+    }
+    let end = if steps.is_empty() { "" } else { "\n" };
+    writeln!(out, "{}]", end)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::day_2::Instruction::{Down, Forward};
+    use crate::error::Error;
+    use crate::export::{Format, write_course};
+    use crate::solution::Part;
+
+    fn export(format: Format) -> String {
+        let mut out = Vec::new();
+        write_course(&mut out, &[Forward(5), Down(2), Forward(3)], Part::Two, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!("step,instruction,forward,depth,aim\n1,forward 5,5,0,0\n2,down 2,5,0,2\n3,forward 3,8,6,2\n", export(Format::Csv));
+    }
+
+    #[test]
+    fn test_json() {
+        let expected = "[\n  {\"step\": 1, \"instruction\": \"forward 5\", \"forward\": 5, \"depth\": 0, \"aim\": 0},\n  \
+            {\"step\": 2, \"instruction\": \"down 2\", \"forward\": 5, \"depth\": 0, \"aim\": 2},\n  \
+            {\"step\": 3, \"instruction\": \"forward 3\", \"forward\": 8, \"depth\": 6, \"aim\": 2}\n]\n";
+        assert_eq!(expected, export(Format::Json));
+
+        let mut out = Vec::new();
+        write_course(&mut out, &[], Part::One, Format::Json).unwrap();
+        assert_eq!("[]\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_svg_overlays_both_parts() {
+        let svg = export(Format::Svg);
+        assert!(svg.contains("points=\"0,0 5,0 5,2 8,2\""));
+        assert!(svg.contains("points=\"0,0 5,0 5,0 8,6\""));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let err = write_course(&mut Vec::new(), &[Down(u32::MAX), Forward(u32::MAX)], Part::Two, Format::Csv);
+        assert_eq!(Err(Error::Overflow { day: 2, step: 2, quantity: "depth" }), err);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(Ok(Format::Json), Format::from_str("json"));
+        assert_eq!(Ok(Format::Svg), Format::from_str("svg"));
+        assert_eq!(Err(String::from("invalid format \"xml\", expected csv, json or svg")), Format::from_str("xml"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::error::Error;
+
+// Puzzle inputs saved as `<dir>/dayN.txt`, by default under ~/.cache/aoc2021.
+pub struct Cache {
+    dir: PathBuf
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Cache {
+        Cache { dir }
+    }
+
+    pub fn default_dir() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("aoc2021"))
+    }
+
+    pub fn path(&self, day: u8) -> PathBuf {
+        self.dir.join(format!("day{}.txt", day))
+    }
+
+    pub fn load(&self, day: u8) -> Result<Option<String>, Error> {
+        match fs::read_to_string(self.path(day)) {
+            Ok(input) => Ok(Some(input)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into())
+        }
+    }
+
+    pub fn store(&self, day: u8, input: &str) -> Result<(), Error> {
+        fs::create_dir_all(&self.dir)?;
+        Ok(fs::write(self.path(day), input)?)
+    }
+}
+
+// Fetches `<base>/2021/day/N/input` with the session cookie. Only plain http:// is spoken, as
+// there is no TLS without a dependency, so the base URL must be a TLS-terminating proxy on a
+// loopback address; the session token is never sent unencrypted across the network.
+pub struct Downloader {
+    base_url: String,
+    session: String
+}
+
+impl Downloader {
+    pub fn new(base_url: &str, session: &str) -> Downloader {
+        Downloader { base_url: String::from(base_url.trim_end_matches('/')), session: String::from(session) }
+    }
+
+    pub fn fetch(&self, day: u8) -> Result<String, Error> {
+        let fail = |reason: String| Error::Input { day, reason };
+        if self.session.is_empty() || !self.session.chars().all(|c| c.is_ascii_graphic() && c != ';') {
+            return Err(fail(String::from("session token must be non-empty printable text without spaces or \";\"")));
+        }
+        let rest = self.base_url.strip_prefix("http://")
+            .ok_or_else(|| fail(format!("unsupported base URL \"{}\", expected http://", self.base_url)))?;
+        let (host, prefix) = rest.split_once('/').map_or((rest, ""), |(host, path)| (host, path));
+        let (name, port) = split_host(host);
+        if !is_loopback(name) {
+            return Err(fail(format!("refusing to send the session token over plain http to \"{}\", which is not a loopback address", name)));
+        }
+        let address = format!("{}:{}", name, port.unwrap_or("80"));
+        let path = format!("/{}/2021/day/{}/input", prefix, day).replace("//", "/");
+
+        let response = request(&address, host, &path, &self.session).map_err(|err| fail(err.to_string()))?;
+        let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| fail(String::from("malformed response")))?;
+        let status = head.lines().next().and_then(|line| line.split(' ').nth(1)).unwrap_or("");
+        match status {
+            "200" => Ok(String::from(body)),
+            _ => Err(fail(format!("server answered {}", head.lines().next().unwrap_or(""))))
+        }
+    }
+}
+
+// Splits `host[:port]`, where an IPv6 host is written in brackets as in `[::1]:8080`.
+fn split_host(host: &str) -> (&str, Option<&str>) {
+    let port_start = match host.rfind(']') {
+        Some(end) => host[end..].find(':').map(|colon| end + colon),
+        None => host.rfind(':')
+    };
+    match port_start {
+        Some(colon) => (&host[..colon], Some(&host[colon + 1..])),
+        None => (host, None)
+    }
+}
+
+fn is_loopback(name: &str) -> bool {
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+// HTTP/1.0 so the server closes the connection and never chunks the body.
+fn request(address: &str, host: &str, path: &str, session: &str) -> std::io::Result<String> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nCookie: session={}\r\nUser-Agent: aoc2021\r\n\r\n", path, host, session)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+// The cached input, or else a fresh download that is cached for next time.
+pub fn load_input(cache: &Cache, downloader: Option<&Downloader>, day: u8) -> Result<String, Error> {
+    if let Some(input) = cache.load(day)? {
+        return Ok(input);
+    }
+    let downloader = downloader.ok_or_else(|| Error::Input {
+        day,
+        reason: format!("{} is not cached and no session token is set", cache.path(day).display())
+    })?;
+    let input = downloader.fetch(day)?;
+    cache.store(day, &input)?;
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread;
+    use crate::error::Error;
+    use crate::input::{Cache, Downloader, is_loopback, load_input, split_host};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aoc2021-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // Answers one request with the given status and body, and hands back the request head.
+    fn stub_server(status: &'static str, body: &'static str) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let head = BufReader::new(stream.try_clone().unwrap()).lines()
+                .map(Result::unwrap)
+                .take_while(|line| !line.is_empty())
+                .collect();
+            write!(stream, "HTTP/1.0 {}\r\nContent-Type: text/plain\r\n\r\n{}", status, body).unwrap();
+            head
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = temp_dir("cache");
+        let cache = Cache::new(dir.clone());
+        assert_eq!(Ok(None), cache.load(2));
+        cache.store(2, "forward 5\n").unwrap();
+        assert_eq!(dir.join("day2.txt"), cache.path(2));
+        assert_eq!(Ok(Some(String::from("forward 5\n"))), cache.load(2));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_is_cached() {
+        let (base_url, server) = stub_server("200 OK", "199\n200\n208\n");
+        let dir = temp_dir("download");
+        let cache = Cache::new(dir.clone());
+        let downloader = Downloader::new(&format!("{}/", base_url), "abc123");
+        assert_eq!(Ok(String::from("199\n200\n208\n")), load_input(&cache, Some(&downloader), 1));
+
+        let head = server.join().unwrap();
+        assert_eq!("GET /2021/day/1/input HTTP/1.0", head[0]);
+        assert!(head.contains(&String::from("Cookie: session=abc123")));
+        // The stub only answers once, so this must come from the cache.
+        assert_eq!(Ok(String::from("199\n200\n208\n")), load_input(&cache, Some(&downloader), 1));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_errors() {
+        let (base_url, server) = stub_server("404 Not Found", "no such day");
+        let err = Downloader::new(&base_url, "abc123").fetch(26).unwrap_err();
+        assert_eq!("could not load day 26 input: server answered HTTP/1.0 404 Not Found", err.to_string());
+        server.join().unwrap();
+
+        let err = Downloader::new("https://adventofcode.com", "abc123").fetch(1).unwrap_err();
+        assert_eq!(Error::Input { day: 1, reason: String::from("unsupported base URL \"https://adventofcode.com\", expected http://") }, err);
+        let err = Downloader::new("http://127.0.0.1:1", "abc\r\nHost: elsewhere").fetch(1).unwrap_err();
+        assert!(err.to_string().contains("session token"));
+    }
+
+    #[test]
+    fn test_plain_http_only_to_loopback() {
+        let err = Downloader::new("http://adventofcode.com", "abc123").fetch(1).unwrap_err();
+        let reason = "refusing to send the session token over plain http to \"adventofcode.com\", which is not a loopback address";
+        assert_eq!(Error::Input { day: 1, reason: String::from(reason) }, err);
+        for base_url in ["http://10.0.0.1:8080/aoc", "http://127.0.0.1.example.com", "http://[2001:db8::1]:80"] {
+            assert!(Downloader::new(base_url, "abc123").fetch(1).unwrap_err().to_string().contains("refusing"), "{}", base_url);
+        }
+
+        assert_eq!(("localhost", Some("8080")), split_host("localhost:8080"));
+        assert_eq!(("[::1]", Some("8080")), split_host("[::1]:8080"));
+        assert_eq!(("[::1]", None), split_host("[::1]"));
+        assert!(["localhost", "LOCALHOST", "127.0.0.1", "127.1.2.3", "[::1]"].iter().all(|name| is_loopback(name)));
+    }
+
+    #[test]
+    fn test_missing_without_session() {
+        let cache = Cache::new(temp_dir("missing"));
+        let err = load_input(&cache, None, 4).unwrap_err();
+        assert!(err.to_string().ends_with("day4.txt is not cached and no session token is set"));
+    }
+}
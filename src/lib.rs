@@ -0,0 +1,11 @@
+pub mod day_1;
+pub mod day_2;
+pub mod day_3;
+pub mod day_4;
+pub mod error;
+pub mod export;
+pub mod input;
+pub mod parse;
+pub mod render;
+pub mod solution;
+pub mod stats;
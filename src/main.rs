@@ -1,26 +1,141 @@
-use crate::day_1::{count_increases, count_windowed_increases};
-use crate::day_2::{travel, travel_part2};
-use crate::day_3::{power_consumption, life_support_rating};
-use crate::day_4::{day4_part1};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::process::exit;
+use std::{env, io};
+use aoc2021::day_2::{Limits, model, parse_course, parse_course_lines, validate};
+use aoc2021::day_3::{read_report, Settings, statistics};
+use aoc2021::export::write_course;
+use aoc2021::input::{Cache, Downloader, load_input};
+use aoc2021::parse::ParseContext;
+use aoc2021::solution::{DAYS, find_day};
+use aoc2021::stats::write_statistics;
+use crate::cli::{Command, ExportArgs, parse_args, RunArgs, Source, StatsArgs, USAGE};
 
-mod day_1;
-mod day_2;
-mod day_3;
-mod day_4;
+mod cli;
 
-fn main() {
-    let day1_input = include_str!("day1.txt");
-    println!("Day 1 part 1 result: {}", count_increases(day1_input));
-    println!("Day 1 part 2 result: {}", count_windowed_increases(day1_input));
+fn open_input(day: u8, source: &Source) -> Result<Box<dyn BufRead>, String> {
+    let reader: Box<dyn BufRead> = match source {
+        Source::File(path) => Box::new(BufReader::new(File::open(path).map_err(|err| format!("could not read input: {}", err))?)),
+        Source::Stdin => Box::new(io::stdin().lock()),
+        Source::Cache => Box::new(Cursor::new(cached_input(day)?))
+    };
+    Ok(reader)
+}
+
+fn cached_input(day: u8) -> Result<String, String> {
+    let dir = Cache::default_dir().ok_or("no cache directory, as HOME is not set")?;
+    let downloader = match (env::var("AOC_SESSION").ok(), env::var("AOC_BASE_URL").ok()) {
+        (Some(session), Some(base_url)) => Some(Downloader::new(&base_url, &session)),
+        (Some(_), None) => return Err(String::from("AOC_SESSION is set but AOC_BASE_URL is not; set it to a local TLS-terminating proxy")),
+        (None, _) => None
+    };
+    load_input(&Cache::new(dir), downloader.as_ref(), day).map_err(|err| err.to_string())
+}
 
-    let day2_input = include_str!("day2.txt");
-    println!("Day 2 {}", travel(day2_input));
-    println!("Day2 part 2: {}", travel_part2(day2_input));
+fn read_input(day: u8, source: &Source) -> Result<String, String> {
+    let mut input = String::new();
+    open_input(day, source)?
+        .read_to_string(&mut input)
+        .map_err(|err| format!("could not read input: {}", err))?;
+    Ok(input)
+}
+
+fn print_warnings(context: &ParseContext) {
+    for warning in context.warnings() {
+        eprintln!("warning: {} (skipped)", warning);
+    }
+}
 
-    let day3_input = include_str!("day3.txt");
-    println!("Day 3 part 1 result: {}", power_consumption(day3_input));
-    println!("Day 3 part 2 result: {}", life_support_rating(day3_input));
+fn run(args: &RunArgs) -> Result<(), String> {
+    let day = find_day(args.day).ok_or(format!("no solution for day {}", args.day))?;
+    let mut input = open_input(args.day, &args.input)?;
+    let mut context = ParseContext::new(args.mode);
+    args.options.iter().try_for_each(|(name, value)| context.add_option(name, value)).map_err(|err| err.to_string())?;
+    let answers = day.solve_reader(&mut input, &args.parts(), &mut context);
+    print_warnings(&context);
+    let answers = answers.map_err(|err| err.to_string())?;
+    for answer in answers {
+        println!("Day {} part {} result: {}", day.number, answer.part.number(), answer.value);
+    }
+    Ok(())
+}
 
-    let day4_input = include_str!("day4.txt");
-    println!("Day 4 part 1 result: {}", day4_part1(day4_input));
+fn export(args: &ExportArgs) -> Result<(), String> {
+    if args.day != 2 {
+        return Err(format!("no export for day {}", args.day));
+    }
+    let input = read_input(args.day, &args.input)?;
+    let mut context = ParseContext::new(args.mode);
+    let course = parse_course(&input, &mut context);
+    print_warnings(&context);
+    let course = course.map_err(|err| err.to_string())?;
+    write_course(&mut io::stdout().lock(), &course, args.part, args.format).map_err(|err| err.to_string())
+}
+
+fn check_course(args: &RunArgs) -> Result<(), String> {
+    if args.day != 2 {
+        return Err(format!("no validator for day {}", args.day));
+    }
+    let input = read_input(args.day, &args.input)?;
+    let mut context = ParseContext::new(args.mode);
+    args.options.iter().try_for_each(|(name, value)| context.add_option(name, value)).map_err(|err| err.to_string())?;
+    context.check_options(Limits::OPTIONS).map_err(|err| err.to_string())?;
+    let course = parse_course_lines(&input, &mut context);
+    print_warnings(&context);
+    let course = course.map_err(|err| err.to_string())?;
+    let limits = Limits::from_options(&context).map_err(|err| err.to_string())?;
+    let mut count = 0;
+    for part in args.parts() {
+        let violations = validate(&course, model(part), &limits).map_err(|err| err.to_string())?;
+        for violation in &violations {
+            println!("Day 2 part {} {}", part.number(), violation);
+        }
+        count += violations.len();
+    }
+    match count {
+        0 => Ok(()),
+        1 => Err(String::from("course has 1 limit violation")),
+        _ => Err(format!("course has {} limit violations", count))
+    }
+}
+
+fn stats(args: &StatsArgs) -> Result<(), String> {
+    if args.day != 3 {
+        return Err(format!("no statistics for day {}", args.day));
+    }
+    let input = read_input(args.day, &args.input)?;
+    let mut context = ParseContext::new(args.mode);
+    args.options.iter().try_for_each(|(name, value)| context.add_option(name, value)).map_err(|err| err.to_string())?;
+    context.check_options(Settings::OPTIONS).map_err(|err| err.to_string())?;
+    let settings = Settings::from_options(&context).map_err(|err| err.to_string())?;
+    let report = read_report(&input, settings.width, &mut context);
+    print_warnings(&context);
+    let report = report.map_err(|err| err.to_string())?;
+    let stats = statistics(&report, &settings).map_err(|err| err.to_string())?;
+    write_statistics(&mut io::stdout().lock(), &stats, args.layout).map_err(|err| err.to_string())
+}
+
+fn main() {
+    let command = parse_args(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("error: {}\n\n{}", err, USAGE);
+        exit(2)
+    });
+    let result = match command {
+        Command::Run(args) => run(&args),
+        Command::Export(args) => export(&args),
+        Command::Validate(args) => check_course(&args),
+        Command::Stats(args) => stats(&args),
+        Command::List => {
+            DAYS.iter().for_each(|day| println!("Day {}: {}", day.number, day.title));
+            Ok(())
+        }
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        exit(1)
+    }
 }
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::num::{IntErrorKind, ParseIntError};
+use crate::error::{Error, ParseError, ParseErrorKind};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Mode {
+    #[default]
+    Strict,
+    Lenient
+}
+
+#[derive(Debug, Default)]
+pub struct ParseContext {
+    mode: Mode,
+    options: BTreeMap<String, String>,
+    warnings: Vec<ParseError>
+}
+
+impl ParseContext {
+    pub fn new(mode: Mode) -> ParseContext {
+        ParseContext { mode, options: BTreeMap::new(), warnings: Vec::new() }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // Day-specific settings such as `win=rows,diagonals`, read by the day's parser.
+    pub fn set_option(&mut self, name: &str, value: &str) {
+        self.options.insert(String::from(name), String::from(value));
+    }
+
+    // Like `set_option`, but a name that is already set is rejected instead of replaced, so an
+    // option repeated on the command line cannot silently override the first value.
+    pub fn add_option(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        if self.options.contains_key(name) {
+            return Err(Error::invalid_option(name, value, "option is given more than once"));
+        }
+        self.set_option(name, value);
+        Ok(())
+    }
+
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    // Rejects the first option whose name is not in `known`, so a misspelt name is never ignored.
+    pub fn check_options(&self, known: &[&str]) -> Result<(), Error> {
+        match self.options.iter().find(|(name, _)| !known.contains(&name.as_str())) {
+            Some((name, value)) => {
+                let reason = match known {
+                    [] => String::from("unknown option, none are accepted here"),
+                    _ => format!("unknown option, expected {}", known.join(", "))
+                };
+                Err(Error::invalid_option(name, value, &reason))
+            }
+            None => Ok(())
+        }
+    }
+
+    pub fn warnings(&self) -> &[ParseError] {
+        &self.warnings
+    }
+
+    // In strict mode a bad item fails the parse; in lenient mode it is recorded as a warning and skipped.
+    pub fn recover<T>(&mut self, result: Result<T, ParseError>) -> Result<Option<T>, ParseError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if self.mode == Mode::Lenient => {
+                self.warnings.push(err);
+                Ok(None)
+            }
+            Err(err) => Err(err)
+        }
+    }
+}
+
+pub(crate) fn number_error(err: &ParseIntError) -> ParseErrorKind {
+    match err.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => ParseErrorKind::OutOfRange,
+        _ => ParseErrorKind::InvalidNumber
+    }
+}
+
+pub(crate) fn split_columns(line: &str, separator: char) -> impl Iterator<Item = (usize, &str)> {
+    let mut column = 1;
+    line.split(separator).map(move |token| {
+        let start = column;
+        column += token.len() + separator.len_utf8();
+        (start, token)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ParseError, ParseErrorKind};
+    use crate::parse::{Mode, number_error, ParseContext, split_columns};
+
+    #[test]
+    fn test_split_columns() {
+        let tokens = split_columns(" 8  2 23", ' ').filter(|(_, token)| !token.is_empty()).collect::<Vec<(usize, &str)>>();
+        assert_eq!(vec![(2, "8"), (5, "2"), (7, "23")], tokens);
+    }
+
+    #[test]
+    fn test_number_error() {
+        assert_eq!(ParseErrorKind::OutOfRange, number_error(&"4294967296".parse::<u32>().unwrap_err()));
+        assert_eq!(ParseErrorKind::InvalidNumber, number_error(&"-1".parse::<u32>().unwrap_err()));
+        assert_eq!(ParseErrorKind::InvalidNumber, number_error(&"".parse::<u32>().unwrap_err()));
+    }
+
+    #[test]
+    fn test_recover() {
+        let err = ParseError::new(1, 2, 1, "x", ParseErrorKind::InvalidNumber);
+
+        let mut strict = ParseContext::new(Mode::Strict);
+        assert_eq!(Ok(Some(1)), strict.recover(Ok(1)));
+        assert_eq!(Err(err.clone()), strict.recover::<u32>(Err(err.clone())));
+        assert!(strict.warnings().is_empty());
+
+        let mut lenient = ParseContext::new(Mode::Lenient);
+        assert_eq!(Ok(None), lenient.recover::<u32>(Err(err.clone())));
+        assert_eq!(&[err], lenient.warnings());
+    }
+
+    #[test]
+    fn test_options() {
+        let mut context = ParseContext::default();
+        assert_eq!(None, context.option("win"));
+        context.set_option("win", "rows");
+        context.set_option("win", "diagonals");
+        assert_eq!(Some("diagonals"), context.option("win"));
+    }
+
+    #[test]
+    fn test_add_option() {
+        let mut context = ParseContext::default();
+        assert_eq!(Ok(()), context.add_option("window", "2"));
+        let err = context.add_option("window", "1").unwrap_err();
+        assert_eq!("invalid value \"1\" for option window: option is given more than once", err.to_string());
+        assert_eq!(Some("2"), context.option("window"));
+    }
+
+    #[test]
+    fn test_check_options() {
+        let mut context = ParseContext::default();
+        assert_eq!(Ok(()), context.check_options(&[]));
+        context.set_option("window", "2");
+        assert_eq!(Ok(()), context.check_options(&["window", "threshold"]));
+
+        context.set_option("windw", "2");
+        let err = context.check_options(&["window", "threshold"]).unwrap_err();
+        assert_eq!("invalid value \"2\" for option windw: unknown option, expected window, threshold", err.to_string());
+        let err = context.check_options(&[]).unwrap_err();
+        assert_eq!("invalid value \"2\" for option window: unknown option, none are accepted here", err.to_string());
+    }
+}
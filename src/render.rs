@@ -0,0 +1,86 @@
+use std::io::Write;
+use crate::day_2::Step;
+
+const WIDTH: i64 = 800;
+const HEIGHT: i64 = 400;
+const MARGIN: i64 = 40;
+const PART1_COLOUR: &str = "#1f77b4";
+const PART2_COLOUR: &str = "#d62728";
+
+// Plots horizontal position against depth for both interpretations, starting from the origin.
+// The plot area's viewBox is in course units, so every coordinate is written as an integer and
+// the output is byte-for-byte reproducible; depth grows downwards like the SVG y axis.
+pub fn write_svg<W: Write>(out: &mut W, part1: &[Step], part2: &[Step]) -> std::io::Result<()> {
+    let part1 = points(part1);
+    let part2 = points(part2);
+    let (min_x, min_y, max_x, max_y) = part1.iter().chain(part2.iter())
+        .fold((0, 0, 0, 0), |(min_x, min_y, max_x, max_y), &(x, y)| (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)));
+    writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", WIDTH, HEIGHT, WIDTH, HEIGHT)?;
+    writeln!(out, "  <title>Day 2 course</title>")?;
+    writeln!(out, "  <rect width=\"{}\" height=\"{}\" fill=\"white\"/>", WIDTH, HEIGHT)?;
+    writeln!(out, "  <text x=\"{}\" y=\"24\" font-family=\"sans-serif\" font-size=\"14\" fill=\"{}\">part 1</text>", MARGIN, PART1_COLOUR)?;
+    writeln!(out, "  <text x=\"{}\" y=\"24\" font-family=\"sans-serif\" font-size=\"14\" fill=\"{}\">part 2</text>", MARGIN + 80, PART2_COLOUR)?;
+    writeln!(out, "  <svg x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\" preserveAspectRatio=\"none\" overflow=\"visible\">",
+             MARGIN, MARGIN, WIDTH - 2 * MARGIN, HEIGHT - 2 * MARGIN,
+             min_x, min_y, extent(min_x, max_x), extent(min_y, max_y))?;
+    writeln!(out, "    <line x1=\"{}\" y1=\"0\" x2=\"{}\" y2=\"0\" stroke=\"#999999\" stroke-width=\"1\" vector-effect=\"non-scaling-stroke\"/>", min_x, max_x)?;
+    write_polyline(out, "part 1", PART1_COLOUR, &part1)?;
+    write_polyline(out, "part 2", PART2_COLOUR, &part2)?;
+    writeln!(out, "  </svg>")?;
+    writeln!(out, "</svg>")
+}
+
+// In i128, as a course may span more than the whole i64 range.
+fn extent(min: i64, max: i64) -> i128 {
+    (i128::from(max) - i128::from(min)).max(1)
+}
+
+fn points(steps: &[Step]) -> Vec<(i64, i64)> {
+    std::iter::once((0, 0))
+        .chain(steps.iter().map(|step| (step.state.forward, step.state.depth)))
+        .collect()
+}
+
+fn write_polyline<W: Write>(out: &mut W, title: &str, colour: &str, points: &[(i64, i64)]) -> std::io::Result<()> {
+    let points = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect::<Vec<String>>().join(" ");
+    writeln!(out, "    <polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" vector-effect=\"non-scaling-stroke\" points=\"{}\"><title>{}</title></polyline>",
+             colour, points, title)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_2::{Aimed, Direct, parse_course, Step, trajectory};
+    use crate::error::Error;
+    use crate::parse::ParseContext;
+    use crate::render::write_svg;
+
+    #[test]
+    fn test_example_golden() {
+        let course = parse_course(include_str!("../examples/day2_example.txt"), &mut ParseContext::default()).unwrap();
+        let part1 = trajectory(&course, &Direct).collect::<Result<Vec<Step>, Error>>().unwrap();
+        let part2 = trajectory(&course, &Aimed).collect::<Result<Vec<Step>, Error>>().unwrap();
+        let mut out = Vec::new();
+        write_svg(&mut out, &part1, &part2).unwrap();
+        assert_eq!(include_str!("../examples/day2_example.svg"), String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_empty_course() {
+        let mut out = Vec::new();
+        write_svg(&mut out, &[], &[]).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.contains("viewBox=\"0 0 1 1\""));
+        assert!(svg.contains("points=\"0,0\""));
+    }
+
+    #[test]
+    fn test_extent_wider_than_i64() {
+        let input = "down 1610612736\nforward 4294967295\nup 2684354560\nforward 4294967295\nforward 4294967295\nforward 4294967295\n";
+        let course = parse_course(input, &mut ParseContext::default()).unwrap();
+        let part2 = trajectory(&course, &Aimed).collect::<Result<Vec<Step>, Error>>().unwrap();
+        let mut out = Vec::new();
+        write_svg(&mut out, &[], &part2).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.contains("viewBox=\"0 -6917529026030469120 17179869180 13835058052060938240\""));
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt::Display;
+use std::io::BufRead;
+use crate::{day_1, day_2, day_3, day_4};
+use crate::error::Error;
+use crate::parse::ParseContext;
+
+pub trait Solution {
+    type Input;
+    type Part1: Display;
+    type Part2: Display;
+
+    // The `--option` names the day reads; any other name is rejected before parsing.
+    const OPTIONS: &'static [&'static str];
+
+    fn parse(input: &str, context: &mut ParseContext) -> Result<Self::Input, Error>;
+    fn part1(input: &Self::Input) -> Result<Self::Part1, Error>;
+    fn part2(input: &Self::Input) -> Result<Self::Part2, Error>;
+}
+
+// Solves both parts in a single pass over the input without holding it in memory.
+pub trait StreamingSolution: Solution {
+    fn stream(reader: &mut dyn BufRead, context: &mut ParseContext) -> Result<(Self::Part1, Self::Part2), Error>;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Part {
+    One,
+    Two
+}
+
+impl Part {
+    pub const ALL: [Part; 2] = [Part::One, Part::Two];
+
+    pub fn number(&self) -> u8 {
+        match self {
+            Part::One => 1,
+            Part::Two => 2
+        }
+    }
+}
+
+pub struct Answer {
+    pub part: Part,
+    pub value: String
+}
+
+type Solver = fn(&str, &[Part], &mut ParseContext) -> Result<Vec<Answer>, Error>;
+
+type Streamer = fn(&mut dyn BufRead, &[Part], &mut ParseContext) -> Result<Vec<Answer>, Error>;
+
+pub struct Day {
+    pub number: u8,
+    pub title: &'static str,
+    solve: Solver,
+    stream: Option<Streamer>
+}
+
+impl Day {
+    pub fn solve(&self, input: &str, parts: &[Part], context: &mut ParseContext) -> Result<Vec<Answer>, Error> {
+        (self.solve)(input, parts, context)
+    }
+
+    // Falls back to reading the whole input when the day has no streaming solution.
+    pub fn solve_reader(&self, reader: &mut dyn BufRead, parts: &[Part], context: &mut ParseContext) -> Result<Vec<Answer>, Error> {
+        match self.stream {
+            Some(stream) => stream(reader, parts, context),
+            None => {
+                let mut input = String::new();
+                reader.read_to_string(&mut input)?;
+                self.solve(&input, parts, context)
+            }
+        }
+    }
+}
+
+fn solve<S: Solution>(input: &str, parts: &[Part], context: &mut ParseContext) -> Result<Vec<Answer>, Error> {
+    context.check_options(S::OPTIONS)?;
+    let parsed = S::parse(input, context)?;
+    parts.iter()
+        .map(|part| {
+            let value = match part {
+                Part::One => S::part1(&parsed)?.to_string(),
+                Part::Two => S::part2(&parsed)?.to_string()
+            };
+            Ok(Answer { part: *part, value })
+        })
+        .collect()
+}
+
+fn stream<S: StreamingSolution>(reader: &mut dyn BufRead, parts: &[Part], context: &mut ParseContext) -> Result<Vec<Answer>, Error> {
+    context.check_options(S::OPTIONS)?;
+    let (part1, part2) = S::stream(reader, context)?;
+    Ok(parts.iter()
+        .map(|part| {
+            let value = match part {
+                Part::One => part1.to_string(),
+                Part::Two => part2.to_string()
+            };
+            Answer { part: *part, value }
+        })
+        .collect())
+}
+
+pub const DAYS: &[Day] = &[
+    Day { number: 1, title: "Sonar Sweep", solve: solve::<day_1::Day1>, stream: Some(stream::<day_1::Day1>) },
+    Day { number: 2, title: "Dive!", solve: solve::<day_2::Day2>, stream: None },
+    Day { number: 3, title: "Binary Diagnostic", solve: solve::<day_3::Day3>, stream: None },
+    Day { number: 4, title: "Giant Squid", solve: solve::<day_4::Day4>, stream: None },
+];
+
+pub fn find_day(number: u8) -> Option<&'static Day> {
+    DAYS.iter().find(|day| day.number == number)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::parse::{Mode, ParseContext};
+    use crate::solution::{DAYS, find_day, Part};
+
+    #[test]
+    fn test_days_are_ordered() {
+        let numbers = DAYS.iter().map(|day| day.number).collect::<Vec<u8>>();
+        assert_eq!(vec![1, 2, 3, 4], numbers);
+    }
+
+    #[test]
+    fn test_find_day() {
+        assert_eq!("Dive!", find_day(2).unwrap().title);
+        assert!(find_day(25).is_none());
+    }
+
+    #[test]
+    fn test_solve_reports_parse_error() {
+        let err = find_day(2).unwrap().solve("forward 5\nsideways 3\n", &Part::ALL, &mut ParseContext::default()).err().unwrap();
+        assert_eq!("day2 line 2: unknown action \"sideways\"", err.to_string());
+    }
+
+    #[test]
+    fn test_solve_lenient_collects_warnings() {
+        let mut context = ParseContext::new(Mode::Lenient);
+        let answers = find_day(1).unwrap().solve("1\n2\nthree\n4\n", &Part::ALL, &mut context).unwrap();
+        assert_eq!("2", answers[0].value);
+        assert_eq!(1, context.warnings().len());
+        assert_eq!(3, context.warnings()[0].line);
+    }
+
+    #[test]
+    fn test_solve_example() {
+        let answers = find_day(2).unwrap().solve(include_str!("../examples/day2_example.txt"), &Part::ALL, &mut ParseContext::default()).unwrap();
+        assert_eq!(vec!["150", "900"], answers.iter().map(|answer| answer.value.as_str()).collect::<Vec<&str>>());
+    }
+
+    #[test]
+    fn test_solve_reader_streams() {
+        let answers = find_day(1).unwrap().solve_reader(&mut Cursor::new("199\n200\n208\n"), &[Part::One], &mut ParseContext::default()).unwrap();
+        assert_eq!("2", answers[0].value);
+    }
+
+    #[test]
+    fn test_solve_reader_without_streaming() {
+        let mut reader = Cursor::new(include_str!("../examples/day2_example.txt"));
+        let answers = find_day(2).unwrap().solve_reader(&mut reader, &[Part::One], &mut ParseContext::default()).unwrap();
+        assert_eq!("150", answers[0].value);
+    }
+
+    #[test]
+    fn test_solve_rejects_unknown_options() {
+        let mut context = ParseContext::default();
+        context.set_option("windw", "2");
+        let err = find_day(1).unwrap().solve_reader(&mut Cursor::new("1\n"), &Part::ALL, &mut context).err().unwrap();
+        assert_eq!("invalid value \"2\" for option windw: unknown option, expected window, aggregate, threshold, counts", err.to_string());
+
+        let mut context = ParseContext::default();
+        context.set_option("wn", "diagonals");
+        let err = find_day(4).unwrap().solve(include_str!("../examples/day4_example.txt"), &Part::ALL, &mut context).err().unwrap();
+        assert_eq!("invalid value \"diagonals\" for option wn: unknown option, expected win", err.to_string());
+
+        let mut context = ParseContext::default();
+        context.set_option("width", "5");
+        assert!(find_day(2).unwrap().solve("forward 5\n", &Part::ALL, &mut context).is_err());
+        assert!(find_day(3).unwrap().solve("10110\n", &Part::ALL, &mut context).is_ok());
+    }
+
+    #[test]
+    fn test_solve_single_part() {
+        let answers = find_day(2).unwrap().solve(include_str!("../examples/day2_example.txt"), &[Part::Two], &mut ParseContext::default()).unwrap();
+        assert_eq!(1, answers.len());
+        assert_eq!(Part::Two, answers[0].part);
+        assert_eq!("900", answers[0].value);
+    }
+}
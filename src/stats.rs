@@ -0,0 +1,134 @@
+use std::io::Write;
+use std::str::FromStr;
+use crate::day_3::Statistics;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Layout {
+    Table,
+    Json
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Layout::Table),
+            "json" => Ok(Layout::Json),
+            _ => Err(format!("invalid format \"{}\", expected table or json", s))
+        }
+    }
+}
+
+pub fn write_statistics<W: Write>(out: &mut W, stats: &Statistics, layout: Layout) -> std::io::Result<()> {
+    match layout {
+        Layout::Table => write_table(out, stats),
+        Layout::Json => write_json(out, stats)
+    }
+}
+
+// Each value as (name, value if any); a rating that matched no rows has none.
+fn values(stats: &Statistics) -> [(&'static str, Option<u64>); 4] {
+    [("gamma", Some(stats.gamma)), ("epsilon", Some(stats.epsilon)), ("oxygen", stats.oxygen.value()), ("co2", stats.co2.value())]
+}
+
+fn write_table<W: Write>(out: &mut W, stats: &Statistics) -> std::io::Result<()> {
+    writeln!(out, "{} rows, {} columns", stats.rows, stats.width)?;
+    writeln!(out)?;
+    writeln!(out, "position   ones  zeros  ratio  tie")?;
+    for column in &stats.columns {
+        writeln!(out, "{:>8} {:>6} {:>6} {:>6.3}  {}", column.position, column.ones, column.zeros, column.ratio(),
+                 if column.tie() { "yes" } else { "no" })?;
+    }
+    writeln!(out)?;
+    for (name, value) in values(stats) {
+        match value {
+            Some(value) => writeln!(out, "{:<8} {:0width$b} {} {:#x}", name, value, value, value, width = stats.width)?,
+            None => writeln!(out, "{:<8} none", name)?
+        }
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(out: &mut W, stats: &Statistics) -> std::io::Result<()> {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"rows\": {},", stats.rows)?;
+    writeln!(out, "  \"width\": {},", stats.width)?;
+    write!(out, "  \"columns\": [")?;
+    for (index, column) in stats.columns.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        write!(out, "{}\n    {{\"position\": {}, \"ones\": {}, \"zeros\": {}, \"ratio\": {:.3}, \"tie\": {}}}",
+               separator, column.position, column.ones, column.zeros, column.ratio(), column.tie())?;
+    }
+    writeln!(out, "{}],", if stats.columns.is_empty() { "" } else { "\n  " })?;
+    let values = values(stats);
+    for (index, (name, value)) in values.iter().enumerate() {
+        let separator = if index + 1 == values.len() { "" } else { "," };
+        match value {
+            Some(value) => writeln!(out, "  \"{}\": {{\"binary\": \"{:0width$b}\", \"decimal\": {}, \"hex\": \"{:#x}\"}}{}",
+                                    name, value, value, value, separator, width = stats.width)?,
+            None => writeln!(out, "  \"{}\": null{}", name, separator)?
+        }
+    }
+    writeln!(out, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::day_3::{read_input, Report, Settings, statistics};
+    use crate::parse::ParseContext;
+    use crate::stats::{Layout, write_statistics};
+
+    fn render(report: &Report, layout: Layout) -> String {
+        let mut out = Vec::new();
+        write_statistics(&mut out, &statistics(report, &Settings::default()).unwrap(), layout).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_table() {
+        let report = read_input(include_str!("../examples/day3_example.txt"), &mut ParseContext::default()).unwrap();
+        let expected = "\
+12 rows, 5 columns
+
+position   ones  zeros  ratio  tie
+       0      7      5  0.583  no
+       1      5      7  0.417  no
+       2      8      4  0.667  no
+       3      7      5  0.583  no
+       4      5      7  0.417  no
+
+gamma    10110 22 0x16
+epsilon  01001 9 0x9
+oxygen   10111 23 0x17
+co2      01010 10 0xa
+";
+        assert_eq!(expected, render(&report, Layout::Table));
+    }
+
+    #[test]
+    fn test_json() {
+        let expected = "\
+{
+  \"rows\": 2,
+  \"width\": 2,
+  \"columns\": [
+    {\"position\": 0, \"ones\": 2, \"zeros\": 0, \"ratio\": 1.000, \"tie\": false},
+    {\"position\": 1, \"ones\": 2, \"zeros\": 0, \"ratio\": 1.000, \"tie\": false}
+  ],
+  \"gamma\": {\"binary\": \"11\", \"decimal\": 3, \"hex\": \"0x3\"},
+  \"epsilon\": {\"binary\": \"00\", \"decimal\": 0, \"hex\": \"0x0\"},
+  \"oxygen\": {\"binary\": \"11\", \"decimal\": 3, \"hex\": \"0x3\"},
+  \"co2\": null
+}
+";
+        assert_eq!(expected, render(&Report::new(2, vec![0b11, 0b11]).unwrap(), Layout::Json));
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        assert_eq!(Ok(Layout::Table), Layout::from_str("table"));
+        assert_eq!(Err(String::from("invalid format \"csv\", expected table or json")), Layout::from_str("csv"));
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use aoc2021::parse::ParseContext;
+use aoc2021::solution::{Day, DAYS, find_day, Part};
+
+// One manifest entry: the answer `day` must give for `part` of `file` under `options`.
+struct Case {
+    line: usize,
+    file: String,
+    day: u8,
+    part: Part,
+    answer: String,
+    options: Vec<(String, String)>
+}
+
+fn examples_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("examples")
+}
+
+fn read_manifest() -> Vec<Case> {
+    let manifest = fs::read_to_string(examples_dir().join("answers.txt")).unwrap();
+    manifest.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| parse_case(index + 1, line).unwrap_or_else(|err| panic!("answers.txt line {}: {}", index + 1, err)))
+        .collect()
+}
+
+fn parse_case(line: usize, text: &str) -> Result<Case, String> {
+    let mut fields = text.split_whitespace();
+    let mut field = |name| fields.next().ok_or(format!("missing {}", name));
+    let file = String::from(field("input file")?);
+    let day = field("day")?.parse::<u8>().map_err(|err| format!("invalid day: {}", err))?;
+    let part = match field("part")? {
+        "1" => Part::One,
+        "2" => Part::Two,
+        other => return Err(format!("invalid part \"{}\"", other))
+    };
+    let answer = String::from(field("answer")?);
+    let options = fields
+        .map(|option| option.split_once('=')
+            .map(|(name, value)| (String::from(name), String::from(value)))
+            .ok_or(format!("invalid option \"{}\", expected NAME=VALUE", option)))
+        .collect::<Result<Vec<(String, String)>, String>>()?;
+    Ok(Case { line, file, day, part, answer, options })
+}
+
+// Solves the case both from a string and from a reader, which streams where the day supports it.
+fn check(case: &Case) -> Result<(), String> {
+    let day: &Day = find_day(case.day).ok_or(format!("no solution for day {}", case.day))?;
+    let input = fs::read_to_string(examples_dir().join(&case.file)).map_err(|err| format!("{}: {}", case.file, err))?;
+    let context = || {
+        let mut context = ParseContext::default();
+        case.options.iter().for_each(|(name, value)| context.add_option(name, value).unwrap());
+        context
+    };
+    let solved = day.solve(&input, &[case.part], &mut context()).map_err(|err| err.to_string())?;
+    let streamed = day.solve_reader(&mut Cursor::new(&input), &[case.part], &mut context()).map_err(|err| err.to_string())?;
+    for (how, answers) in [("solve", solved), ("solve_reader", streamed)] {
+        if answers[0].value != case.answer {
+            return Err(format!("{} gave {}, expected {}", how, answers[0].value, case.answer));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_examples_match_manifest() {
+    let failures = read_manifest().iter()
+        .filter_map(|case| check(case).err().map(|err| format!("answers.txt line {} ({} day {} part {}): {}",
+                                                              case.line, case.file, case.day, case.part.number(), err)))
+        .collect::<Vec<String>>();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_every_day_is_covered() {
+    let cases = read_manifest();
+    for day in DAYS {
+        for part in Part::ALL {
+            let covered = cases.iter().any(|case| case.day == day.number && case.part == part && case.options.is_empty());
+            assert!(covered, "answers.txt has no plain entry for day {} part {}", day.number, part.number());
+        }
+    }
+}
+
+#[test]
+fn test_parse_case() {
+    let case = parse_case(3, "day4.txt 4 2 1924 win=rows,diagonals").unwrap();
+    assert_eq!(("day4.txt", 4, Part::Two, "1924"), (case.file.as_str(), case.day, case.part, case.answer.as_str()));
+    assert_eq!(vec![(String::from("win"), String::from("rows,diagonals"))], case.options);
+    assert_eq!(Some(String::from("invalid part \"3\"")), parse_case(1, "day1.txt 1 3 7").err());
+    assert_eq!(Some(String::from("missing answer")), parse_case(1, "day1.txt 1 1").err());
+}